@@ -12,6 +12,10 @@ pub struct AccountData {
 }
 
 impl AccountData {
+    pub fn new(color: RGBColor) -> Self {
+        Self{color}
+    }
+
     pub fn color(&self) -> RGBColor {
         self.color
     }
@@ -0,0 +1,90 @@
+use std::collections::BTreeSet;
+
+use crate::tags::{Tags, TagRef};
+use crate::accounts::AccountRef;
+use crate::transaction::Transactions;
+use crate::money::{CentsAmount, SignedCentsAmount};
+use crate::datetime::Date;
+use crate::formatting::{Formatting, Unit};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Under,
+    /// Within the budget so far, but not at the current flow.
+    Projected,
+    Over,
+}
+
+/// Monthly budget of a tag compared to what an account consumed of it and its descendants.
+#[derive(Debug, Clone)]
+pub struct BudgetStatus {
+    pub tag: TagRef,
+    pub budget: CentsAmount,
+    /// Consumed since the beginning of the month.
+    pub spent: CentsAmount,
+    /// Consumed over a whole month at the current flow.
+    pub projected: CentsAmount,
+}
+
+impl BudgetStatus {
+    /// Share of the budget spent, in percent.
+    pub fn percent(&self) -> u64 {
+        match self.budget.cents() {
+            0 if self.spent.cents() == 0 => 0,
+            0 => u64::MAX,
+            budget => self.spent.cents() * 100 / budget,
+        }
+    }
+
+    pub fn percent_string(&self) -> String {
+        match self.percent() {
+            percent if percent > 999 => ">999%".to_string(),
+            percent => format!("{percent}%"),
+        }
+    }
+
+    pub fn level(&self) -> Level {
+        if self.spent > self.budget {
+            Level::Over
+        } else if self.projected > self.budget {
+            Level::Projected
+        } else {
+            Level::Under
+        }
+    }
+
+    pub fn describe(&self, format: &Formatting) -> String {
+        let amount = |amount: CentsAmount| format.with_symbol(&amount.as_string_exact(format.marks(true)), Unit::Currency);
+        format!("{} {} ({} of {})", self.tag, self.percent_string(), amount(self.spent), amount(self.budget))
+    }
+}
+
+/// Status of every tag with a budget in the month of `today`, in tree order.
+pub fn statuses(transactions: &Transactions, tags: &Tags, account: &AccountRef, today: &Date) -> Vec<BudgetStatus> {
+    let spent = tags.rollup(&transactions.spending_between(&today.first_of_month(), &today.last_of_month(), account), SignedCentsAmount::new(0));
+    let flows = tags.rollup(&transactions.flows_at(today, account, tags), CentsAmount::new(0));
+    let days = u64::from(today.days_in_month());
+
+    tags.tree(&BTreeSet::new()).into_iter().filter_map(|tag| {
+        let budget = tags.0[&tag].budget()?;
+        // Refunds exceeding the purchases of the month do not count as savings.
+        let spent = CentsAmount::new(u64::try_from(-spent[&tag].cents()).unwrap_or(0));
+        let projected = CentsAmount::new(flows[&tag].cents() * days);
+        Some(BudgetStatus{tag, budget, spent, projected})
+    }).collect()
+}
+
+/// `statuses` from the most to the least used budget.
+pub fn ranked(mut statuses: Vec<BudgetStatus>) -> Vec<BudgetStatus> {
+    statuses.sort_by_key(|status| std::cmp::Reverse((status.percent(), status.projected.cents() * 100 / status.budget.cents().max(1))));
+    statuses
+}
+
+/// Warning about the budgets of `tag` and its ancestors that are exceeded, if any.
+pub fn alert(statuses: &[BudgetStatus], tag: &TagRef, tags: &Tags, format: &Formatting) -> Option<String> {
+    let over: Vec<String> = statuses.iter()
+        .filter(|status| tags.is_descendant(tag, &status.tag) && status.level() == Level::Over)
+        .map(|status| status.describe(format))
+        .collect();
+    (!over.is_empty()).then(|| format!("Over budget: {}", over.join(", ")))
+}
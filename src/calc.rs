@@ -0,0 +1,224 @@
+use crate::money::CentsAmount;
+
+/// Results from 100 million units on are rejected, so that sums of amounts cannot overflow.
+const MAX_CENTS: i128 = 100_000_000 * 100;
+
+/// Exact fraction, kept reduced with a positive denominator.
+#[derive(Debug, Copy, Clone)]
+struct Ratio {
+    num: i128,
+    den: i128,
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    if b == 0 { a.abs() } else { gcd(b, a % b) }
+}
+
+impl Ratio {
+    fn new(num: i128, den: i128) -> Option<Self> {
+        if den == 0 {
+            return None;
+        }
+        let divisor = gcd(num, den) * den.signum();
+        Some(Self{num: num / divisor, den: den / divisor})
+    }
+
+    fn add(self, other: Self) -> Option<Self> {
+        Self::new(self.num.checked_mul(other.den)?.checked_add(other.num.checked_mul(self.den)?)?, self.den.checked_mul(other.den)?)
+    }
+
+    fn neg(self) -> Self {
+        Self{num: -self.num, den: self.den}
+    }
+
+    fn mul(self, other: Self) -> Option<Self> {
+        Self::new(self.num.checked_mul(other.num)?, self.den.checked_mul(other.den)?)
+    }
+
+    fn inv(self) -> Option<Self> {
+        Self::new(self.den, self.num)
+    }
+
+    /// Nearest number of cents, halves rounded away from zero.
+    fn cents(self) -> Option<i128> {
+        let hundredfold = self.num.checked_mul(100)?;
+        let (quotient, remainder) = (hundredfold / self.den, hundredfold % self.den);
+        Some(quotient + (2 * remainder.abs() >= self.den) as i128 * hundredfold.signum())
+    }
+}
+
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl Parser<'_> {
+    fn peek(&mut self) -> Option<char> {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+        self.chars.peek().copied()
+    }
+
+    fn expr(&mut self) -> Option<Ratio> {
+        let mut ret = self.term()?;
+        loop {
+            match self.peek() {
+                Some('+') => {
+                    self.chars.next();
+                    ret = ret.add(self.term()?)?;
+                },
+                Some('-') => {
+                    self.chars.next();
+                    ret = ret.add(self.term()?.neg())?;
+                },
+                _ => return Some(ret),
+            }
+        }
+    }
+
+    fn term(&mut self) -> Option<Ratio> {
+        let mut ret = self.factor()?;
+        loop {
+            match self.peek() {
+                Some('*') => {
+                    self.chars.next();
+                    ret = ret.mul(self.factor()?)?;
+                },
+                Some('/') => {
+                    self.chars.next();
+                    ret = ret.mul(self.factor()?.inv()?)?;
+                },
+                _ => return Some(ret),
+            }
+        }
+    }
+
+    fn factor(&mut self) -> Option<Ratio> {
+        match self.peek()? {
+            '-' => {
+                self.chars.next();
+                Some(self.factor()?.neg())
+            },
+            '(' => {
+                self.chars.next();
+                let ret = self.expr()?;
+                if self.peek() != Some(')') {
+                    return None;
+                }
+                self.chars.next();
+                Some(ret)
+            },
+            _ => self.number(),
+        }
+    }
+
+    /// Digits with an optional decimal part after `.` or `,`.
+    fn number(&mut self) -> Option<Ratio> {
+        let (mut num, mut den, mut decimals) = (0i128, 1i128, false);
+        let mut digits = 0;
+        while let Some(c) = self.chars.peek().copied() {
+            if let Some(digit) = c.to_digit(10) {
+                num = num.checked_mul(10)?.checked_add(digit.into())?;
+                if decimals {
+                    den = den.checked_mul(10)?;
+                }
+                digits += 1;
+            } else if (c == '.' || c == ',') && !decimals {
+                decimals = true;
+            } else {
+                break;
+            }
+            self.chars.next();
+        }
+        if digits == 0 {
+            return None;
+        }
+        Ratio::new(num, den)
+    }
+}
+
+/// Whether `text` has an operator or parentheses, rather than being a plain number.
+pub fn is_expression(text: &str) -> bool {
+    text.contains(['+', '-', '*', '/', '(', ')'])
+}
+
+/// Value of an expression of decimal numbers with `+`, `-`, `*`, `/` and parentheses,
+/// computed exactly then rounded to the cent. Negative and too large results are rejected.
+pub fn evaluate(text: &str) -> Option<CentsAmount> {
+    let mut parser = Parser{chars: text.chars().peekable()};
+    let ret = parser.expr()?;
+    if parser.peek().is_some() {
+        return None;
+    }
+    let cents = ret.cents()?;
+    if cents >= MAX_CENTS {
+        return None;
+    }
+    u64::try_from(cents).ok().map(CentsAmount::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cents(text: &str) -> Option<u64> {
+        evaluate(text).map(|amount| amount.cents())
+    }
+
+    #[test]
+    fn plain_numbers() {
+        assert_eq!(cents("12"), Some(1200));
+        assert_eq!(cents("12.5"), Some(1250));
+        assert_eq!(cents("12,05"), Some(1205));
+        assert_eq!(cents("12."), Some(1200));
+        assert_eq!(cents(""), None);
+        assert_eq!(cents("."), None);
+        assert_eq!(cents("1.2.3"), None);
+        assert_eq!(cents("1,2.3"), None);
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(cents("2+3*4"), Some(1400));
+        assert_eq!(cents("(2+3)*4"), Some(2000));
+        assert_eq!(cents("10-4-3"), Some(300));
+        assert_eq!(cents("12/4/3"), Some(100));
+        assert_eq!(cents(" 1 + 2 "), Some(300));
+        assert_eq!(cents("(1+2"), None);
+        assert_eq!(cents("1+2)"), None);
+        assert_eq!(cents("1+"), None);
+    }
+
+    #[test]
+    fn unary_minus() {
+        assert_eq!(cents("-2+5"), Some(300));
+        assert_eq!(cents("5*-2+20"), Some(1000));
+        assert_eq!(cents("--3"), Some(300));
+        assert_eq!(cents("2-(3-4)"), Some(300));
+        assert_eq!(cents("-3"), None);
+        assert_eq!(cents("1-2"), None);
+    }
+
+    #[test]
+    fn division_by_zero() {
+        assert_eq!(cents("1/0"), None);
+        assert_eq!(cents("1/(2-2)"), None);
+        assert_eq!(cents("0/1"), Some(0));
+    }
+
+    #[test]
+    fn rounding() {
+        assert_eq!(cents("10/3"), Some(333));
+        assert_eq!(cents("20/3"), Some(667));
+        assert_eq!(cents("0.005"), Some(1));
+        assert_eq!(cents("0.0049"), Some(0));
+        assert_eq!(cents("0,125"), Some(13));
+        assert_eq!(cents("1/3*3"), Some(100));
+    }
+
+    #[test]
+    fn bounds() {
+        assert_eq!(cents("99999999.99"), Some(9_999_999_999));
+        assert_eq!(cents("100000000"), None);
+        assert_eq!(cents("99999999*99999999"), None);
+        assert_eq!(cents("9999999999999999999999999999999999999999"), None);
+    }
+}
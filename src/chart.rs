@@ -0,0 +1,203 @@
+use std::error;
+use std::fmt;
+use std::path::Path;
+use std::collections::BTreeMap;
+use chrono::NaiveDate;
+use plotters::prelude::*;
+use plotters::coord::Shift;
+
+use crate::tags::{Tags, TagRef};
+use crate::accounts::{Accounts, AccountRef};
+use crate::transaction::{Transactions, Transaction};
+use crate::moneystate::{Balances, FlowStatesSnapshot};
+use crate::money::CentsAmount;
+use crate::datetime::Date;
+use crate::formatting::Formatting;
+
+#[derive(Debug)]
+pub enum Error {
+    UnknownFormat(String),
+    EmptyRange,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::UnknownFormat(ref path) =>
+                write!(f, "Unknown chart format for \"{path}\", expected .svg or .png"),
+            Error::EmptyRange =>
+                write!(f, "Chart date range is empty"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+#[derive(Debug, Clone)]
+pub struct ChartOptions {
+    pub from: Date,
+    pub to: Date,
+    /// Tags to plot, rolled up with their descendants. Root tags when empty.
+    pub tags: Vec<TagRef>,
+    pub weekly: bool,
+}
+
+impl ChartOptions {
+    /// Whole history up to today, at least the last month, per root tag, daily.
+    pub fn new(transactions: &Transactions) -> Self {
+        let to = Date::today();
+        let mut from = to.clone();
+        for _ in 0..30 {
+            from.decr();
+        }
+        if let Some(first) = transactions.vec().first() {
+            from = Date::min(from, first.date().clone());
+        }
+        Self{from, to, tags: Vec::new(), weekly: false}
+    }
+}
+
+type Series = Vec<(NaiveDate, f64)>;
+
+fn to_units(cents: i64) -> f64 {
+    cents as f64 / 100.
+}
+
+fn weekly(series: Series) -> Series {
+    series.chunks(7).map(|chunk| (chunk[0].0, chunk.iter().map(|(_, x)| x).sum::<f64>() / chunk.len() as f64)).collect()
+}
+
+/// Daily flow of `account` for each selected tag, stepping a snapshot over the history.
+pub fn flow_series(transactions: &Transactions, tags: &Tags, account: &AccountRef, options: &ChartOptions) -> Vec<(TagRef, Series)> {
+    let selected = if options.tags.is_empty() { tags.children(None) } else { options.tags.clone() };
+    let mut ret: Vec<(TagRef, Series)> = selected.iter().map(|tag| (tag.clone(), Vec::new())).collect();
+
+    let start = transactions.vec().first().map_or_else(|| options.from.clone(), |x| Date::min(x.date().clone(), options.from.clone()));
+    let mut snapshot = FlowStatesSnapshot::new(start, tags);
+    let mut iter = transactions.vec().iter().peekable();
+
+    while snapshot.date() <= &options.to {
+        while let Some(tr) = iter.next_if(|tr| tr.date() == snapshot.date()) {
+            if let Transaction::Purchase(purchase) = tr {
+                snapshot.add(purchase, account, tags);
+            }
+        }
+
+        if snapshot.date() >= &options.from {
+            let flows: BTreeMap<TagRef, CentsAmount> = snapshot.state().0.iter().map(|(tag, state)| (tag.clone(), state.flow().0)).collect();
+            let rolled = tags.rollup(&flows, CentsAmount::new(0));
+            for (tag, series) in ret.iter_mut() {
+                let value = rolled.get(tag).map_or(0, |x| x.cents() as i64);
+                series.push((snapshot.date().naive(), to_units(value)));
+            }
+        }
+
+        snapshot.step();
+    }
+
+    if options.weekly {
+        ret = ret.into_iter().map(|(tag, series)| (tag, weekly(series))).collect();
+    }
+
+    ret
+}
+
+/// Cumulative external balance of each account at the end of each day.
+pub fn balance_series(transactions: &Transactions, accounts: &Accounts, options: &ChartOptions) -> Vec<(AccountRef, Series)> {
+    let mut ret: Vec<(AccountRef, Series)> = accounts.0.keys().map(|account| (account.clone(), Vec::new())).collect();
+
+    let mut balances = Balances::new();
+    let mut iter = transactions.vec().iter().peekable();
+    let mut date = options.from.clone();
+
+    while let Some(tr) = iter.next_if(|tr| tr.date() < &date) {
+        balances.add(tr);
+    }
+
+    while date <= options.to {
+        while let Some(tr) = iter.next_if(|tr| tr.date() == &date) {
+            balances.add(tr);
+        }
+
+        for (account, series) in ret.iter_mut() {
+            let value = balances.0.get(account).map_or(0, |x| x.external().cents());
+            series.push((date.naive(), to_units(value)));
+        }
+
+        date.incr();
+    }
+
+    ret
+}
+
+fn value_range<'a>(series: impl Iterator<Item = &'a Series>) -> std::ops::Range<f64> {
+    let values: Vec<f64> = series.flat_map(|s| s.iter().map(|(_, x)| *x)).collect();
+    let min = values.iter().cloned().fold(0., f64::min);
+    let max = values.iter().cloned().fold(0., f64::max);
+    let margin = f64::max((max - min) * 0.05, 1.);
+    (min - margin)..(max + margin)
+}
+
+fn draw_area<DB: DrawingBackend>(root: DrawingArea<DB, Shift>, flows: &[(TagRef, Series)], balances: &[(AccountRef, Series)], accounts: &Accounts, options: &ChartOptions, format: &Formatting) -> Result<(), Box<dyn error::Error>>
+where
+    DB::ErrorType: 'static,
+{
+    root.fill(&WHITE)?;
+    let (top, bottom) = root.split_vertically(root.dim_in_pixel().1 / 2);
+    let dates = options.from.naive()..options.to.naive();
+
+    let caption = if options.weekly { "Weekly flow per tag" } else { "Daily flow per tag" };
+    let mut chart = ChartBuilder::on(&top)
+        .caption(caption, ("sans-serif", 20))
+        .margin(10)
+        .x_label_area_size(30)
+        .y_label_area_size(60)
+        .build_cartesian_2d(dates.clone(), value_range(flows.iter().map(|(_, s)| s)))?;
+    chart.configure_mesh().x_labels(8).x_label_formatter(&|date| format.date(&Date::from(*date))).draw()?;
+    for (index, (tag, series)) in flows.iter().enumerate() {
+        let color = Palette99::pick(index).to_rgba();
+        chart.draw_series(LineSeries::new(series.iter().cloned(), color.stroke_width(2)))?
+            .label(tag.as_str())
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(2)));
+    }
+    chart.configure_series_labels().background_style(WHITE.mix(0.8)).border_style(BLACK).draw()?;
+
+    let mut chart = ChartBuilder::on(&bottom)
+        .caption("Cumulative balance per account", ("sans-serif", 20))
+        .margin(10)
+        .x_label_area_size(30)
+        .y_label_area_size(60)
+        .build_cartesian_2d(dates, value_range(balances.iter().map(|(_, s)| s)))?;
+    chart.configure_mesh().x_labels(8).x_label_formatter(&|date| format.date(&Date::from(*date))).draw()?;
+    for (account, series) in balances {
+        let color: plotters::style::RGBColor = accounts.0[account].color().into();
+        chart.draw_series(LineSeries::new(series.iter().cloned(), color.stroke_width(2)))?
+            .label(account.as_str())
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(2)));
+    }
+    chart.configure_series_labels().background_style(WHITE.mix(0.8)).border_style(BLACK).draw()?;
+
+    root.present()?;
+    Ok(())
+}
+
+/// Renders the flow and balance charts to `path`, as SVG or PNG depending on its extension.
+pub fn draw(path: &Path, transactions: &Transactions, tags: &Tags, accounts: &Accounts, account: &AccountRef, options: &ChartOptions, format: &Formatting) -> Result<(), Box<dyn error::Error>> {
+    let extension = path.extension().and_then(|x| x.to_str());
+    if !matches!(extension, Some("svg" | "png")) {
+        return Err(Error::UnknownFormat(path.display().to_string()).into());
+    }
+    if options.from >= options.to {
+        return Err(Error::EmptyRange.into());
+    }
+
+    let flows = flow_series(transactions, tags, account, options);
+    let balances = balance_series(transactions, accounts, options);
+    let size = (1200, 900);
+
+    if extension == Some("svg") {
+        draw_area(SVGBackend::new(path, size).into_drawing_area(), &flows, &balances, accounts, options, format)
+    } else {
+        draw_area(BitMapBackend::new(path, size).into_drawing_area(), &flows, &balances, accounts, options, format)
+    }
+}
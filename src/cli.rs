@@ -0,0 +1,560 @@
+use std::error;
+use std::fmt;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{stdout, BufWriter, Write};
+use std::rc::Rc;
+use std::cell::RefCell;
+
+use crate::interface::LocalCfg;
+use crate::tags::{Tags, TagRef};
+use crate::accounts::{Accounts, AccountRef};
+use crate::transaction::{Transactions, Transaction, Purchase, Consumers};
+use crate::money::*;
+use crate::formatting::Formatting;
+use crate::datetime::Date;
+use crate::yamlrw::YamlRW;
+use crate::ledger::*;
+use crate::chart::{self, ChartOptions};
+use crate::recurring;
+use crate::budget;
+use crate::report::{Report, Period, Totals};
+use crate::currency::{self, Rates, ForeignAmount};
+use crate::import::{self, ImportCfg, ImportReview, Rules};
+use crate::term::TermElement;
+use crate::export;
+use crate::journal;
+
+#[derive(Debug)]
+pub enum Error {
+    Usage(String),
+    MissingOption(String),
+    InvalidValue(String, String),
+    UnknownTag(String),
+    UnknownAccount(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Usage(ref msg) =>
+                write!(f, "{msg}\n\n{USAGE}"),
+            Error::MissingOption(ref name) =>
+                write!(f, "Missing option \"--{name}\""),
+            Error::InvalidValue(ref name, ref value) =>
+                write!(f, "Invalid value \"{value}\" for option \"--{name}\""),
+            Error::UnknownTag(ref tag) =>
+                write!(f, "Unknown tag \"{tag}\""),
+            Error::UnknownAccount(ref account) =>
+                write!(f, "Unknown account \"{account}\""),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+const USAGE: &str = "\
+Usage:
+  expenses [--dir DIR] [--ledger NAME] [COMMAND]
+
+Commands:
+  expenses add [--date DATE] --amount AMOUNT [--currency CODE] --desc DESC --tag TAG --buyer ACCOUNT --consumer ACCOUNT[:WEIGHT]...
+  expenses list [--from DATE] [--to DATE] [--tag TAG]... [--account ACCOUNT]
+  expenses balance
+  expenses flow [--date DATE]
+  expenses tags
+  expenses budget [--date DATE]
+  expenses report [--period month|year] [--date DATE]
+  expenses chart --output FILE.svg|FILE.png [--from DATE] [--to DATE] [--tag TAG]... [--step day|week]
+  expenses import --csv FILE | --ofx FILE | --qif FILE | --journal FILE
+  expenses export [--output FILE] [--format csv|json|ledger] [--from DATE] [--to DATE] [--tag TAG]... [--account ACCOUNT]
+  expenses ledgers
+
+The ledger directory defaults to $EXPENSES_DIR, then $XDG_CONFIG_HOME/expenses.
+Named ledgers are subdirectories of it, also selected with $EXPENSES_LEDGER.
+Amounts in other currencies are converted when entered, with the rates of rates.yaml in the ledger.
+Monthly budgets are set per tag in tags.yaml, counting the tag and its descendants.
+Imports read the tag rules, the CSV column mapping and the QIF date format from import.yaml in the ledger.";
+
+struct Options(Vec<(String, String)>);
+
+impl Options {
+    fn parse(args: &[String], allowed: &[&str]) -> Result<Self, Error> {
+        let mut ret = Vec::new();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let name = arg.strip_prefix("--").ok_or_else(|| Error::Usage(format!("Unexpected argument \"{arg}\"")))?;
+            if !allowed.contains(&name) {
+                return Err(Error::Usage(format!("Unknown option \"{arg}\"")));
+            }
+            let value = iter.next().ok_or_else(|| Error::Usage(format!("Missing value for \"{arg}\"")))?;
+            ret.push((name.to_string(), value.clone()));
+        }
+        Ok(Self(ret))
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.0.iter().rev().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+    }
+
+    fn get_all(&self, name: &str) -> Vec<&str> {
+        self.0.iter().filter(|(key, _)| key == name).map(|(_, value)| value.as_str()).collect()
+    }
+
+    fn require(&self, name: &str) -> Result<&str, Error> {
+        self.get(name).ok_or_else(|| Error::MissingOption(name.to_string()))
+    }
+
+    fn date(&self, name: &str, format: &Formatting) -> Result<Option<Date>, Error> {
+        self.get(name).map(|value| format.parse_date(value).ok_or_else(|| Error::InvalidValue(name.to_string(), value.to_string()))).transpose()
+    }
+}
+
+struct Ledger {
+    cfg: LocalCfg,
+    tags: Tags,
+    accounts: Accounts,
+    rates: Rates,
+    transactions: Transactions,
+}
+
+impl Ledger {
+    /// Reads the ledger without writing to any of its files.
+    fn load(dir: &LedgerDir) -> Result<Self, Box<dyn error::Error>> {
+        let cfg = LocalCfg::load(dir)?;
+
+        let mut tags = Tags::read_yaml(dir.file(TAGS_FILE))?;
+        tags.fix()?;
+
+        let accounts = Accounts::read_yaml(dir.file(ACCOUNTS_FILE))?;
+
+        let transactions = Transactions::load(dir)?;
+        let rates = currency::load(dir)?;
+        if let Some(warning) = currency::missing_warning(&rates.missing(&transactions)) {
+            eprintln!("{warning}");
+        }
+
+        Ok(Self{cfg, tags, accounts, rates, transactions})
+    }
+
+    /// Same as `load`, also generating the due recurring transactions, for the commands saving the ledger.
+    fn load_for_update(dir: &LedgerDir) -> Result<Self, Box<dyn error::Error>> {
+        let mut ret = Self::load(dir)?;
+        let (_, missing) = recurring::materialize(dir, &ret.tags, &ret.accounts, &ret.rates, &mut ret.transactions)?;
+        if let Some(warning) = currency::missing_warning(&missing) {
+            eprintln!("{warning}");
+        }
+        Ok(ret)
+    }
+
+    fn check_tag(&self, tag: &str) -> Result<TagRef, Error> {
+        if self.tags.0.contains_key(tag) {
+            Ok(tag.to_string())
+        } else {
+            Err(Error::UnknownTag(tag.to_string()))
+        }
+    }
+
+    fn check_account(&self, account: &str) -> Result<AccountRef, Error> {
+        if self.accounts.0.contains_key(account) {
+            Ok(account.to_string())
+        } else {
+            Err(Error::UnknownAccount(account.to_string()))
+        }
+    }
+}
+
+/// Date range and tags selecting transactions.
+struct Filter {
+    from: Option<Date>,
+    to: Option<Date>,
+    tags: Vec<TagRef>,
+}
+
+impl Filter {
+    fn parse(options: &Options, ledger: &Ledger) -> Result<Self, Error> {
+        let tags = options.get_all("tag").into_iter().map(|tag| ledger.check_tag(tag)).collect::<Result<_, _>>()?;
+        Ok(Self{from: options.date("from", ledger.cfg.format())?, to: options.date("to", ledger.cfg.format())?, tags})
+    }
+
+    /// Transfers only match when no tag is given, purchases when their tag descends from one of them.
+    fn matches(&self, transaction: &Transaction, tags: &Tags) -> bool {
+        let date = transaction.date();
+        if self.from.as_ref().is_some_and(|from| date < from) || self.to.as_ref().is_some_and(|to| date > to) {
+            return false;
+        }
+        match transaction {
+            _ if self.tags.is_empty() => true,
+            Transaction::Purchase(purchase) => self.tags.iter().any(|tag| tags.is_descendant(&purchase.tag, tag)),
+            Transaction::Transfer(_) => false,
+        }
+    }
+}
+
+/// Splits the leading global options from the command and its arguments.
+pub fn global_options(args: &[String]) -> Result<(LedgerDir, &[String]), Error> {
+    let mut dir = None;
+    let mut name = None;
+    let mut rest = args;
+
+    while let Some((arg, tail)) = rest.split_first() {
+        let target = match arg.as_str() {
+            "--dir" => &mut dir,
+            "--ledger" => &mut name,
+            _ => break,
+        };
+        let (value, tail) = tail.split_first().ok_or_else(|| Error::Usage(format!("Missing value for \"{arg}\"")))?;
+        *target = Some(value.clone());
+        rest = tail;
+    }
+
+    Ok((LedgerDir::resolve(dir, name), rest))
+}
+
+pub fn run(dir: &LedgerDir, args: &[String]) -> Result<(), Box<dyn error::Error>> {
+    let (command, args) = args.split_first().ok_or_else(|| Error::Usage("Missing command".to_string()))?;
+
+    match command.as_str() {
+        "add" => add(dir, Options::parse(args, &["date", "amount", "currency", "desc", "tag", "buyer", "consumer"])?),
+        "list" => list(dir, Options::parse(args, &["from", "to", "tag", "account"])?),
+        "balance" => balance(dir, Options::parse(args, &[])?),
+        "flow" => flow(dir, Options::parse(args, &["date"])?),
+        "tags" => tags(dir, Options::parse(args, &[])?),
+        "budget" => budget(dir, Options::parse(args, &["date"])?),
+        "report" => report(dir, Options::parse(args, &["period", "date"])?),
+        "chart" => chart(dir, Options::parse(args, &["output", "from", "to", "tag", "step"])?),
+        "export" => export(dir, Options::parse(args, &["output", "format", "from", "to", "tag", "account"])?),
+        "import" => import(dir, Options::parse(args, &["csv", "ofx", "qif", "journal"])?),
+        "ledgers" => ledgers(dir, Options::parse(args, &[])?),
+        "help" | "--help" | "-h" => {
+            println!("{USAGE}");
+            Ok(())
+        },
+        _ => Err(Error::Usage(format!("Unknown command \"{command}\"")).into()),
+    }
+}
+
+fn add(dir: &LedgerDir, options: Options) -> Result<(), Box<dyn error::Error>> {
+    let mut ledger = Ledger::load_for_update(dir)?;
+
+    let date = options.date("date", ledger.cfg.format())?.unwrap_or_else(Date::today);
+
+    let amount = options.require("amount")?;
+    let amount = CentsAmount::parse(amount).filter(|x| x.cents() != 0).ok_or_else(|| Error::InvalidValue("amount".to_string(), amount.to_string()))?;
+
+    let foreign = options.get("currency").map(|currency| ForeignAmount{currency: currency.to_string(), amount});
+
+    let desc = options.require("desc")?.to_string();
+
+    let tag = ledger.check_tag(options.require("tag")?)?;
+
+    let buyer = ledger.check_account(options.require("buyer")?)?;
+
+    let mut consumers = BTreeMap::new();
+    for consumer in options.get_all("consumer") {
+        let (account, weight) = match consumer.split_once(':') {
+            Some((account, weight)) => (account, weight.parse().ok().filter(|x| *x > 0).ok_or_else(|| Error::InvalidValue("consumer".to_string(), consumer.to_string()))?),
+            None => (consumer, 1),
+        };
+        consumers.insert(ledger.check_account(account)?, weight);
+    }
+    if consumers.is_empty() {
+        return Err(Error::MissingOption("consumer".to_string()).into());
+    }
+
+    let mut purchase = Purchase{date: date.clone(), amount, foreign, desc, tag: tag.clone(), buyer, consumers: Consumers(consumers), recurring: None, import_id: None};
+    ledger.rates.apply(&mut purchase)?;
+    ledger.transactions.add(Transaction::Purchase(purchase));
+    ledger.transactions.write_yaml_with_backup(dir.file(DATA_FILE), BACKUP_DAYS)?;
+
+    let statuses = budget::statuses(&ledger.transactions, &ledger.tags, ledger.cfg.account(), &date);
+    if let Some(alert) = budget::alert(&statuses, &tag, &ledger.tags, ledger.cfg.format()) {
+        eprintln!("{alert}");
+    }
+
+    Ok(())
+}
+
+fn list(dir: &LedgerDir, options: Options) -> Result<(), Box<dyn error::Error>> {
+    let ledger = Ledger::load(dir)?;
+
+    let filter = Filter::parse(&options, &ledger)?;
+    let account = options.get("account").map(|x| ledger.check_account(x)).transpose()?;
+
+    let local = ledger.cfg.account();
+    let format = ledger.cfg.format();
+    let marks = format.marks(false);
+
+    for tr in ledger.transactions.vec() {
+        if !filter.matches(tr, &ledger.tags) {
+            continue;
+        }
+        if account.as_ref().is_some_and(|account| !tr.accounts().contains(account)) {
+            continue;
+        }
+
+        let accounts = match tr {
+            Transaction::Purchase(_) => tr.accounts().join(" "),
+            Transaction::Transfer(_) => tr.accounts().join(">"),
+        };
+        let desc = match tr.foreign() {
+            Some(foreign) => format!("{} ({})", tr.desc(), foreign.as_string(marks)),
+            None => tr.desc().clone(),
+        };
+
+        println!("{} {:<12} {:<30} {:<16} {:>10} {:>10} {:>10}",
+            format.date(tr.date()),
+            tr.kind_str(),
+            desc,
+            accounts,
+            tr.internal_delta(local).as_string_exact(marks),
+            tr.external_delta(local).as_string_exact(marks),
+            tr.internal_flow(local, &ledger.tags, &ledger.transactions).0.as_string_exact(marks),
+        );
+    }
+
+    Ok(())
+}
+
+fn balance(dir: &LedgerDir, _options: Options) -> Result<(), Box<dyn error::Error>> {
+    let ledger = Ledger::load(dir)?;
+    let marks = ledger.cfg.format().marks(false);
+
+    let balances = ledger.transactions.balances();
+    for (account, balance) in &balances.0 {
+        println!("{:<16} {:>10}", account, balance.external().as_string_exact(marks));
+    }
+
+    let repayments = balances.settlement();
+    if !repayments.is_empty() {
+        println!();
+    }
+    for repayment in repayments {
+        println!("{} -> {}: {}", repayment.from, repayment.to, repayment.amount.as_string_exact(marks));
+    }
+
+    Ok(())
+}
+
+fn flow(dir: &LedgerDir, options: Options) -> Result<(), Box<dyn error::Error>> {
+    let ledger = Ledger::load(dir)?;
+
+    let date = options.date("date", ledger.cfg.format())?.unwrap_or_else(Date::today);
+    let flows = ledger.transactions.flows_at(&date, ledger.cfg.account(), &ledger.tags);
+    let marks = ledger.cfg.format().marks(false);
+    let rolled = ledger.tags.rollup(&flows, CentsAmount::new(0));
+
+    for tag in ledger.tags.tree(&BTreeSet::new()) {
+        if rolled[&tag].cents() != 0 {
+            let name = format!("{}{}", "  ".repeat(ledger.tags.depth(&tag)), tag);
+            println!("{:<24} {:>10}", name, rolled[&tag].as_string_exact(marks));
+        }
+    }
+    let total = flows.values().fold(CentsAmount::new(0), |acc, x| acc + *x);
+    println!("{:<24} {:>10}", "total", total.as_string_exact(marks));
+
+    Ok(())
+}
+
+fn tags(dir: &LedgerDir, _options: Options) -> Result<(), Box<dyn error::Error>> {
+    let ledger = Ledger::load(dir)?;
+
+    let account = ledger.cfg.account();
+    let marks = ledger.cfg.format().marks(false);
+    let spent = ledger.tags.rollup(&ledger.transactions.spending(account), SignedCentsAmount::new(0));
+    let flows = ledger.tags.rollup(&ledger.transactions.flows_at(&Date::today(), account, &ledger.tags), CentsAmount::new(0));
+
+    for tag in ledger.tags.tree(&BTreeSet::new()) {
+        let name = format!("{}{}", "  ".repeat(ledger.tags.depth(&tag)), tag);
+        println!("{:<24} {:>10} {:>10}", name, spent[&tag].as_string_exact(marks), flows[&tag].as_string_exact(marks));
+    }
+
+    Ok(())
+}
+
+fn budget(dir: &LedgerDir, options: Options) -> Result<(), Box<dyn error::Error>> {
+    let ledger = Ledger::load(dir)?;
+
+    let date = options.date("date", ledger.cfg.format())?.unwrap_or_else(Date::today);
+    let statuses = budget::ranked(budget::statuses(&ledger.transactions, &ledger.tags, ledger.cfg.account(), &date));
+    let marks = ledger.cfg.format().marks(false);
+
+    for status in statuses {
+        let level = match status.level() {
+            budget::Level::Under => "",
+            budget::Level::Projected => "projected over",
+            budget::Level::Over => "over",
+        };
+        let line = format!("{:<24} {:>10} {:>10} {:>10} {:>6} {}", status.tag, status.spent.as_string_exact(marks), status.projected.as_string_exact(marks), status.budget.as_string_exact(marks), status.percent_string(), level);
+        println!("{}", line.trim_end());
+    }
+
+    Ok(())
+}
+
+fn report(dir: &LedgerDir, options: Options) -> Result<(), Box<dyn error::Error>> {
+    let ledger = Ledger::load(dir)?;
+
+    let period = match options.get("period") {
+        Some(name) => Period::from_name(name).ok_or_else(|| Error::InvalidValue("period".to_string(), name.to_string()))?,
+        None => Period::Month,
+    };
+    let date = options.date("date", ledger.cfg.format())?.unwrap_or_else(Date::today);
+    let (current, previous) = Report::with_previous(period, &date, &ledger.transactions, &ledger.tags, &ledger.accounts, ledger.cfg.account());
+
+    let format = ledger.cfg.format();
+    println!("{} ({} to {}), compared to {}", period.label(&date), format.date(&current.from), format.date(&current.to), period.label(&previous.to));
+
+    let marks = format.marks(false);
+    let row = |name: &str, current: &Totals, previous: &Totals| {
+        let [spent, internal, external, flow] = current.changes(previous);
+        println!("{:<24} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}", name,
+            current.spent.as_string_exact(marks), spent.as_string_exact(marks),
+            current.internal.as_string_exact(marks), internal.as_string_exact(marks),
+            current.external.as_string_exact(marks), external.as_string_exact(marks),
+            current.flow.as_string_exact(marks), flow.as_string_exact(marks),
+        );
+    };
+
+    let header = |name: &str| println!("\n{:<24} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}", name, "spent", "change", "internal", "change", "external", "change", "flow", "change");
+
+    header("tag");
+    for tag in ledger.tags.tree(&BTreeSet::new()) {
+        let (current, previous) = (&current.tags[&tag], &previous.tags[&tag]);
+        if current != &Totals::zero() || previous != &Totals::zero() {
+            row(&format!("{}{}", "  ".repeat(ledger.tags.depth(&tag)), tag), current, previous);
+        }
+    }
+
+    header("account");
+    for (account, totals) in &current.accounts {
+        row(account, totals, &previous.accounts[account]);
+    }
+
+    Ok(())
+}
+
+fn chart(dir: &LedgerDir, options: Options) -> Result<(), Box<dyn error::Error>> {
+    let ledger = Ledger::load(dir)?;
+
+    let output = options.require("output")?;
+    let mut chart_options = ChartOptions::new(&ledger.transactions);
+    if let Some(from) = options.date("from", ledger.cfg.format())? {
+        chart_options.from = from;
+    }
+    if let Some(to) = options.date("to", ledger.cfg.format())? {
+        chart_options.to = to;
+    }
+    for tag in options.get_all("tag") {
+        chart_options.tags.push(ledger.check_tag(tag)?);
+    }
+    chart_options.weekly = match options.get("step") {
+        None | Some("day") => false,
+        Some("week") => true,
+        Some(value) => return Err(Error::InvalidValue("step".to_string(), value.to_string()).into()),
+    };
+
+    chart::draw(output.as_ref(), &ledger.transactions, &ledger.tags, &ledger.accounts, ledger.cfg.account(), &chart_options, ledger.cfg.format())
+}
+
+fn import(dir: &LedgerDir, options: Options) -> Result<(), Box<dyn error::Error>> {
+    if let Some(path) = options.get("journal") {
+        return import_journal(dir, path);
+    }
+
+    let mut ledger = Ledger::load_for_update(dir)?;
+
+    let cfg = ImportCfg::read_yaml(dir.file(IMPORT_FILE))?;
+    let rules = Rules::new(&cfg, &ledger.tags, &ledger.accounts, ledger.cfg.account())?;
+    let (entries, ignored) = match (options.get("ofx"), options.get("qif")) {
+        (Some(path), _) => import::read_ofx(path.as_ref())?,
+        (None, Some(path)) => import::read_qif(path.as_ref(), cfg.qif_date_format())?,
+        (None, None) => import::read_csv(options.require("csv")?.as_ref(), cfg.csv()?)?,
+    };
+
+    let mut review = ImportReview::new(import::candidates(entries, &rules, &ledger.transactions), ignored, ledger.tags.clone(), ledger.accounts.clone(), Rc::new(RefCell::new(ledger.cfg.clone())));
+    crate::setup_panic_hook();
+    review.run()?;
+    if !review.committed() {
+        println!("Import cancelled");
+        return Ok(());
+    }
+
+    let purchases = review.included();
+    let count = purchases.len();
+    for purchase in purchases {
+        ledger.transactions.add(Transaction::Purchase(purchase));
+    }
+    ledger.transactions.write_yaml_with_backup(dir.file(DATA_FILE), BACKUP_DAYS)?;
+    println!("Imported {count} transactions");
+
+    Ok(())
+}
+
+/// Adds the journal transactions not already in the ledger, without review.
+fn import_journal(dir: &LedgerDir, path: &str) -> Result<(), Box<dyn error::Error>> {
+    let mut ledger = Ledger::load_for_update(dir)?;
+
+    let text = std::fs::read_to_string(path).map_err(|err| import::Error::File(path.to_string(), err))?;
+    let transactions = journal::parse(&text, &ledger.tags, &ledger.accounts, &ledger.cfg.format().symbol)?;
+
+    let total = transactions.len();
+    let mut existing = ledger.transactions.vec().clone();
+    let mut count = 0;
+    for transaction in transactions {
+        match existing.iter().position(|x| x == &transaction) {
+            Some(index) => {
+                existing.swap_remove(index);
+            },
+            None => {
+                ledger.transactions.add(transaction);
+                count += 1;
+            },
+        }
+    }
+    if count > 0 {
+        ledger.transactions.write_yaml_with_backup(dir.file(DATA_FILE), BACKUP_DAYS)?;
+    }
+    println!("Imported {count} transactions, {} already present", total - count);
+
+    Ok(())
+}
+
+fn export(dir: &LedgerDir, options: Options) -> Result<(), Box<dyn error::Error>> {
+    let ledger = Ledger::load(dir)?;
+
+    let filter = Filter::parse(&options, &ledger)?;
+    let account = match options.get("account") {
+        Some(account) => ledger.check_account(account)?,
+        None => ledger.cfg.account().clone(),
+    };
+    let output = options.get("output");
+    let format = match (options.get("format"), output) {
+        (Some(name), _) => export::Format::from_name(name)?,
+        (None, Some(path)) => export::Format::from_path(path.as_ref())?,
+        (None, None) => export::Format::Csv,
+    };
+
+    let selected = ledger.transactions.vec().iter().filter(|tr| filter.matches(tr, &ledger.tags));
+    match output {
+        Some(path) => {
+            let write_error = |err| export::Error::Write(path.to_string(), err);
+            let mut out = BufWriter::new(File::create(path).map_err(write_error)?);
+            export::write(&mut out, format, selected, &account, &ledger.tags, &ledger.transactions, &ledger.cfg.format().symbol).map_err(write_error)?;
+            out.flush().map_err(write_error)?;
+        },
+        None => export::write(&mut stdout().lock(), format, selected, &account, &ledger.tags, &ledger.transactions, &ledger.cfg.format().symbol)?,
+    }
+
+    Ok(())
+}
+
+fn ledgers(dir: &LedgerDir, _options: Options) -> Result<(), Box<dyn error::Error>> {
+    println!("{}", dir.path().display());
+    for name in dir.named_ledgers() {
+        let marker = if Some(name.as_str()) == dir.name() { "*" } else { " " };
+        println!("{marker} {name}");
+    }
+
+    Ok(())
+}
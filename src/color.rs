@@ -1,5 +1,6 @@
 use serde::{Serialize, Deserialize, Serializer, Deserializer, de::Error};
 use crossterm::style::Color;
+use std::fmt;
 
 #[derive(Debug, Copy, Clone)]
 pub struct RGBColor{
@@ -8,20 +9,34 @@ pub struct RGBColor{
     b: u8,
 }
 
+impl RGBColor {
+    pub fn new(r: u8, g: u8, b: u8) -> Self {
+        Self{r, g, b}
+    }
+
+    pub fn from_hex(text: &str) -> Option<Self> {
+        if text.len() != 6 { return None; }
+        let r = u8::from_str_radix(text.get(0..2)?, 16).ok()?;
+        let g = u8::from_str_radix(text.get(2..4)?, 16).ok()?;
+        let b = u8::from_str_radix(text.get(4..6)?, 16).ok()?;
+
+        Some(Self{r, g, b})
+    }
+}
+
 impl<'de> Deserialize<'de> for RGBColor {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        let error = || D::Error::custom("Color must be 6 hex digits");
-
         let text: String = Deserialize::deserialize(deserializer)?;
-        if text.len() != 6 { return Err(error()); }
-        let r = u8::from_str_radix(text.get(0..2).ok_or_else(|| error())?, 16).map_err(|_| error())?;
-        let g = u8::from_str_radix(text.get(2..4).ok_or_else(|| error())?, 16).map_err(|_| error())?;
-        let b = u8::from_str_radix(text.get(4..6).ok_or_else(|| error())?, 16).map_err(|_| error())?;
+        Self::from_hex(&text).ok_or_else(|| D::Error::custom("Color must be 6 hex digits"))
+    }
+}
 
-        Ok(Self{r, g, b})
+impl fmt::Display for RGBColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02x}{:02x}{:02x}", self.r, self.g, self.b)
     }
 }
 
@@ -30,8 +45,7 @@ impl Serialize for RGBColor {
     where
         S: Serializer,
     {
-        let text = format!("{:02x}{:02x}{:02x}", self.r, self.g, self.b);
-        serializer.serialize_str(&text)
+        serializer.serialize_str(&self.to_string())
     }
 }
 
@@ -40,3 +54,9 @@ impl From<RGBColor> for Color {
         Color::Rgb{r: rgb.r, g: rgb.g, b: rgb.b}
     }
 }
+
+impl From<RGBColor> for plotters::style::RGBColor {
+    fn from(rgb: RGBColor) -> plotters::style::RGBColor {
+        plotters::style::RGBColor(rgb.r, rgb.g, rgb.b)
+    }
+}
@@ -9,7 +9,7 @@ impl Completor {
         Self{matches: dict.clone(), dict}
     }
 
-    pub fn update(&mut self, curr: &String) {
+    pub fn update(&mut self, curr: &str) {
         let lower = curr.to_lowercase();
         self.matches = self.dict.iter().filter(|x| x.to_lowercase().contains(&lower)).map(Clone::clone).collect();
         self.matches.sort_by_cached_key(|x| x.to_lowercase().find(&lower).unwrap());
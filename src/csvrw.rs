@@ -0,0 +1,83 @@
+/// Splits CSV `text` into records of fields. Fields may be quoted with `"`,
+/// in which case they can hold delimiters, newlines and doubled quotes.
+pub fn parse(text: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut ret = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            },
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            c if quoted => field.push(c),
+            c if c == delimiter => record.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => (),
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                ret.push(std::mem::take(&mut record));
+            },
+            c => field.push(c),
+        }
+    }
+
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        ret.push(record);
+    }
+
+    ret.retain(|record| record.iter().any(|field| !field.is_empty()));
+    ret
+}
+
+/// Joins `fields` into a CSV line, quoting the fields that need it.
+pub fn record<S: AsRef<str>>(fields: &[S], delimiter: char) -> String {
+    fields.iter().map(|field| {
+        let field = field.as_ref();
+        if field.contains([delimiter, '"', '\n', '\r']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_string()
+        }
+    }).collect::<Vec<_>>().join(&delimiter.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_fields() {
+        assert_eq!(parse("a,b,c\n1,2,3\n", ','), vec![vec!["a", "b", "c"], vec!["1", "2", "3"]]);
+        assert_eq!(parse("a;b\r\n1;2", ';'), vec![vec!["a", "b"], vec!["1", "2"]]);
+        assert_eq!(parse("\u{feff}a,,c\n\n,\n", ','), vec![vec!["a", "", "c"]]);
+    }
+
+    #[test]
+    fn quoted_fields() {
+        assert_eq!(parse("\"a,b\",\"say \"\"hi\"\"\",c\n", ','), vec![vec!["a,b", "say \"hi\"", "c"]]);
+        assert_eq!(parse("\"\",x", ','), vec![vec!["", "x"]]);
+        assert_eq!(parse("a\"b,c", ','), vec![vec!["a\"b", "c"]]);
+    }
+
+    #[test]
+    fn embedded_newlines() {
+        assert_eq!(parse("\"line 1\nline 2\",x\r\ny,z\r\n", ','), vec![vec!["line 1\nline 2", "x"], vec!["y", "z"]]);
+        assert_eq!(parse("\"a\r\nb\"", ','), vec![vec!["a\r\nb"]]);
+    }
+
+    #[test]
+    fn records() {
+        assert_eq!(record(&["a", "b c", ""], ','), "a,b c,");
+        assert_eq!(record(&["a,b", "say \"hi\"", "x\ny"], ','), "\"a,b\",\"say \"\"hi\"\"\",\"x\ny\"");
+        assert_eq!(record(&["a,b", "c;d"], ';'), "a,b;\"c;d\"");
+
+        let fields = ["1,5", "\"quoted\"", "multi\nline", "plain"];
+        assert_eq!(parse(&record(&fields, ','), ','), vec![fields.to_vec()]);
+    }
+}
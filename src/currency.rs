@@ -0,0 +1,129 @@
+use std::error;
+use std::fmt;
+use std::collections::{BTreeMap, BTreeSet};
+use serde::{Serialize, Deserialize};
+
+use crate::money::{CentsAmount, Marks};
+use crate::datetime::Date;
+use crate::transaction::{Transactions, Transaction, Purchase};
+use crate::ledger::{LedgerDir, RATES_FILE};
+use crate::yamlrw::YamlRW;
+
+#[derive(Debug)]
+pub enum Error {
+    UnknownCurrency(String),
+    InvalidRate(String, Date),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::UnknownCurrency(ref currency) =>
+                write!(f, "No exchange rate for currency \"{currency}\""),
+            Error::InvalidRate(ref currency, ref date) =>
+                write!(f, "Invalid exchange rate for currency \"{currency}\" on {date}"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+/// Amount of a purchase paid in another currency than the one of the ledger.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ForeignAmount {
+    pub currency: String,
+    pub amount: CentsAmount,
+}
+
+impl ForeignAmount {
+    pub fn as_string(&self, marks: Marks) -> String {
+        format!("{} {}", self.amount.as_string_exact(marks), self.currency)
+    }
+}
+
+/// Value in the ledger currency of one unit of each currency, from the given dates on.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Rates(pub BTreeMap<String, BTreeMap<Date, f64>>);
+
+impl YamlRW for Rates {}
+
+impl Rates {
+    pub fn check(&self) -> Result<(), Error> {
+        for (currency, rates) in &self.0 {
+            if rates.is_empty() {
+                return Err(Error::UnknownCurrency(currency.clone()));
+            }
+            if let Some((date, _)) = rates.iter().find(|(_, rate)| !rate.is_finite() || **rate <= 0.0) {
+                return Err(Error::InvalidRate(currency.clone(), date.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn currencies(&self) -> Vec<String> {
+        self.0.keys().cloned().collect()
+    }
+
+    pub fn contains(&self, currency: &str) -> bool {
+        self.0.contains_key(currency)
+    }
+
+    /// Latest rate given on or before `date`, or the first one for earlier dates.
+    pub fn rate(&self, currency: &str, date: &Date) -> Result<f64, Error> {
+        let rates = self.0.get(currency).ok_or_else(|| Error::UnknownCurrency(currency.to_string()))?;
+        rates.range(..=date).next_back().or_else(|| rates.iter().next())
+            .map(|(_, rate)| *rate)
+            .ok_or_else(|| Error::UnknownCurrency(currency.to_string()))
+    }
+
+    pub fn convert(&self, foreign: &ForeignAmount, date: &Date) -> Result<CentsAmount, Error> {
+        let rate = self.rate(&foreign.currency, date)?;
+        Ok(CentsAmount::new((foreign.amount.cents() as f64 * rate).round() as u64))
+    }
+
+    /// Sets the amount of `purchase` to the conversion of its foreign amount, if any.
+    pub fn apply(&self, purchase: &mut Purchase) -> Result<(), Error> {
+        if let Some(foreign) = &purchase.foreign {
+            purchase.amount = self.convert(foreign, &purchase.date)?;
+        }
+        Ok(())
+    }
+
+    pub fn converted(&self, mut transaction: Transaction) -> Result<Transaction, Error> {
+        if let Transaction::Purchase(purchase) = &mut transaction {
+            self.apply(purchase)?;
+        }
+        Ok(transaction)
+    }
+
+    /// Currencies of `transactions` that have no rate. Their amounts, converted
+    /// when entered, stay valid, but they cannot be entered again.
+    pub fn missing(&self, transactions: &Transactions) -> BTreeSet<String> {
+        transactions.vec().iter()
+            .filter_map(|tr| match tr {
+                Transaction::Purchase(purchase) => purchase.foreign.as_ref(),
+                Transaction::Transfer(_) => None,
+            })
+            .filter(|foreign| !self.contains(&foreign.currency))
+            .map(|foreign| foreign.currency.clone())
+            .collect()
+    }
+}
+
+/// Warning about the currencies without an exchange rate, if any.
+pub fn missing_warning(missing: &BTreeSet<String>) -> Option<String> {
+    (!missing.is_empty()).then(|| format!("No exchange rate for {}, add it to {RATES_FILE}", missing.iter().cloned().collect::<Vec<_>>().join(", ")))
+}
+
+/// Exchange rates of the ledger, none if it has no rates file.
+pub fn load(dir: &LedgerDir) -> Result<Rates, Box<dyn error::Error>> {
+    let path = dir.file(RATES_FILE);
+    if !path.exists() {
+        return Ok(Rates::default());
+    }
+
+    let rates = Rates::read_yaml(&path)?;
+    rates.check()?;
+    Ok(rates)
+}
@@ -1,5 +1,6 @@
-use chrono::{Local, NaiveDate};
+use chrono::{Datelike, Local, NaiveDate};
 use serde::{Serialize, Deserialize};
+use std::fmt;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(transparent)]
@@ -8,14 +9,17 @@ pub struct Date {
 }
 
 impl Date {
-    pub const STRING_WIDTH: usize = 2+1+2+1+4;
-
     pub fn today() -> Self {
         Self{date: Local::now().date_naive()}
     }
 
-    pub fn to_string(&self) -> String {
-        self.date.format("%d-%m-%Y").to_string()
+    /// Accepts the default and ISO formats, see `Formatting::parse_date` for the configured one.
+    pub fn parse(text: &str) -> Option<Self> {
+        ["%d-%m-%Y", "%Y-%m-%d"].iter().find_map(|format| NaiveDate::parse_from_str(text, format).ok()).map(|date| Self{date})
+    }
+
+    pub fn naive(&self) -> NaiveDate {
+        self.date
     }
 
     pub fn succ(&self) -> Self {
@@ -33,4 +37,48 @@ impl Date {
     pub fn decr(&mut self) {
         *self = self.pred();
     }
+
+    /// Date `days` days later, or earlier when negative, if it is in the supported range.
+    pub fn plus_days(&self, days: i64) -> Option<Self> {
+        let date = match days >= 0 {
+            true => self.date.checked_add_days(chrono::Days::new(days.unsigned_abs())),
+            false => self.date.checked_sub_days(chrono::Days::new(days.unsigned_abs())),
+        };
+        date.map(|date| Self{date})
+    }
+
+    /// Same day `months` months later, or earlier when negative, moved to the last day of shorter months.
+    pub fn plus_months(&self, months: i32) -> Self {
+        let date = match months >= 0 {
+            true => self.date.checked_add_months(chrono::Months::new(months.unsigned_abs())),
+            false => self.date.checked_sub_months(chrono::Months::new(months.unsigned_abs())),
+        };
+        Self{date: date.unwrap_or(self.date)}
+    }
+
+    pub fn first_of_month(&self) -> Self {
+        Self{date: self.date.with_day(1).unwrap()}
+    }
+
+    pub fn last_of_month(&self) -> Self {
+        let first = self.first_of_month().date;
+        Self{date: first.checked_add_months(chrono::Months::new(1)).unwrap().pred_opt().unwrap()}
+    }
+
+    /// Number of days of the month of the date.
+    pub fn days_in_month(&self) -> u32 {
+        self.last_of_month().date.day()
+    }
+}
+
+impl From<NaiveDate> for Date {
+    fn from(date: NaiveDate) -> Self {
+        Self{date}
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.date.format("%d-%m-%Y"))
+    }
 }
@@ -0,0 +1,129 @@
+use std::error;
+use std::fmt;
+use std::io::Write;
+use std::path::Path;
+
+use crate::tags::Tags;
+use crate::accounts::AccountRef;
+use crate::transaction::{Transactions, Transaction};
+use crate::money::SignedCentsAmount;
+use crate::csvrw;
+use crate::journal;
+
+#[derive(Debug)]
+pub enum Error {
+    UnknownFormat(String),
+    Write(String, std::io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::UnknownFormat(ref name) =>
+                write!(f, "Unknown export format \"{name}\", expected csv, json or ledger"),
+            Error::Write(ref filename, ref err) =>
+                write!(f, "Failed to write \"{filename}\": {err}"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Write(_, ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Json,
+    Ledger,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Result<Self, Error> {
+        match name {
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            "ledger" | "journal" => Ok(Format::Ledger),
+            _ => Err(Error::UnknownFormat(name.to_string())),
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Result<Self, Error> {
+        Self::from_name(&path.extension().map(|x| x.to_string_lossy().into_owned()).unwrap_or_default())
+    }
+}
+
+const COLUMNS: [&str; 9] = ["date", "kind", "tag", "desc", "accounts", "amount", "internal_delta", "external_delta", "internal_flow"];
+
+fn number(amount: SignedCentsAmount) -> String {
+    amount.as_decimal()
+}
+
+fn json_string(text: &str) -> String {
+    let mut ret = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => ret += "\\\"",
+            '\\' => ret += "\\\\",
+            '\n' => ret += "\\n",
+            '\r' => ret += "\\r",
+            '\t' => ret += "\\t",
+            c if c.is_control() => ret += &format!("\\u{:04x}", c as u32),
+            c => ret.push(c),
+        }
+    }
+    ret.push('"');
+    ret
+}
+
+/// Fields of `transaction` in the order of `COLUMNS`, computed for `account`.
+fn fields(transaction: &Transaction, account: &AccountRef, tags: &Tags, transactions: &Transactions) -> [String; 9] {
+    let (kind, tag, separator) = match transaction {
+        Transaction::Purchase(purchase) => ("purchase", purchase.tag.clone(), " "),
+        Transaction::Transfer(_) => ("transfer", String::new(), ">"),
+    };
+    [
+        transaction.date().naive().format("%Y-%m-%d").to_string(),
+        kind.to_string(),
+        tag,
+        transaction.desc().clone(),
+        transaction.accounts().join(separator),
+        number(SignedCentsAmount::positive(transaction.abs_amount())),
+        number(transaction.internal_delta(account)),
+        number(transaction.external_delta(account)),
+        number(transaction.internal_flow(account, tags, transactions).0),
+    ]
+}
+
+/// Writes `selected` with the deltas and flow of `account`, one record or object per transaction.
+/// Journals hold the postings of every account instead, with amounts in `commodity`.
+pub fn write<'a, W: Write>(out: &mut W, format: Format, selected: impl Iterator<Item = &'a Transaction>, account: &AccountRef, tags: &Tags, transactions: &Transactions, commodity: &str) -> std::io::Result<()> {
+    match format {
+        Format::Csv => {
+            writeln!(out, "{}", csvrw::record(&COLUMNS, ','))?;
+            for transaction in selected {
+                writeln!(out, "{}", csvrw::record(&fields(transaction, account, tags, transactions), ','))?;
+            }
+        },
+        Format::Json => {
+            write!(out, "[")?;
+            for (index, transaction) in selected.enumerate() {
+                let values = fields(transaction, account, tags, transactions);
+                let members: Vec<String> = COLUMNS.iter().zip(values).enumerate().map(|(column, (name, value))| {
+                    // Amounts are written as numbers, the other columns as strings.
+                    let value = if column >= 5 { value } else { json_string(&value) };
+                    format!("{}: {}", json_string(name), value)
+                }).collect();
+                write!(out, "{}\n  {{{}}}", if index == 0 { "" } else { "," }, members.join(", "))?;
+            }
+            writeln!(out, "\n]")?;
+        },
+        Format::Ledger => journal::write(out, selected, tags, commodity)?,
+    }
+    Ok(())
+}
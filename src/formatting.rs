@@ -0,0 +1,164 @@
+use std::error;
+use std::fmt;
+use chrono::NaiveDate;
+use chrono::format::{StrftimeItems, Item};
+use serde::{Serialize, Deserialize};
+
+use crate::money::Marks;
+use crate::datetime::Date;
+
+const ISO_DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Debug)]
+pub enum Error {
+    InvalidDateFormat(String),
+    InvalidSeparators,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::InvalidDateFormat(ref format) =>
+                write!(f, "Invalid date format \"{format}\""),
+            Error::InvalidSeparators =>
+                write!(f, "The decimal mark and the thousands separator must be different and not digits"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Placement {
+    Before,
+    #[default]
+    After,
+}
+
+/// What an amount counts, shown by its symbol.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Unit {
+    Currency,
+    Flow,
+}
+
+/// How amounts and dates are shown, from the `format` section of the local config.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Formatting {
+    /// Symbol of the ledger currency.
+    pub symbol: String,
+    /// Symbol of smoothed daily flows.
+    pub flow_symbol: String,
+    pub placement: Placement,
+    pub thousands: Option<char>,
+    pub decimal: char,
+    /// In the syntax of `chrono::format::strftime`.
+    pub date_format: String,
+    /// Shows dates as `YYYY-MM-DD` whatever `date_format` is.
+    pub iso: bool,
+    #[serde(skip)]
+    date_width: usize,
+}
+
+impl Default for Formatting {
+    fn default() -> Self {
+        Self {
+            symbol: "€".to_string(),
+            flow_symbol: "¤".to_string(),
+            placement: Placement::After,
+            thousands: None,
+            decimal: '.',
+            date_format: "%d-%m-%Y".to_string(),
+            iso: false,
+            date_width: 10,
+        }
+    }
+}
+
+impl Formatting {
+    /// Checks the settings read from the config and computes what depends on them.
+    pub fn init(&mut self) -> Result<(), Error> {
+        self.check()?;
+        self.date_width = self.compute_date_width();
+        Ok(())
+    }
+
+    pub fn check(&self) -> Result<(), Error> {
+        if StrftimeItems::new(&self.date_format).any(|item| item == Item::Error) {
+            return Err(Error::InvalidDateFormat(self.date_format.clone()));
+        }
+        if Some(self.decimal) == self.thousands || std::iter::once(self.decimal).chain(self.thousands).any(|c| c.is_ascii_digit() || c == '-' || c == '+') {
+            return Err(Error::InvalidSeparators);
+        }
+        Ok(())
+    }
+
+    pub fn date_format(&self) -> &str {
+        if self.iso { ISO_DATE_FORMAT } else { &self.date_format }
+    }
+
+    /// Widest date of the format, checked on one day of each month for month names.
+    fn compute_date_width(&self) -> usize {
+        (1..=12).filter_map(|month| NaiveDate::from_ymd_opt(2000, month, 28))
+            .map(|date| date.format(self.date_format()).to_string().chars().count())
+            .max().unwrap_or(0)
+    }
+
+    pub fn date_width(&self) -> usize {
+        self.date_width
+    }
+
+    pub fn date(&self, date: &Date) -> String {
+        date.naive().format(self.date_format()).to_string()
+    }
+
+    /// Accepts the configured format besides the ones of `Date::parse`.
+    pub fn parse_date(&self, text: &str) -> Option<Date> {
+        NaiveDate::parse_from_str(text, self.date_format()).ok().map(Date::from).or_else(|| Date::parse(text))
+    }
+
+    /// Marks of amounts, grouping thousands only if `separator` is set.
+    pub fn marks(&self, separator: bool) -> Marks {
+        Marks{decimal: self.decimal, thousands: self.thousands.filter(|_| separator)}
+    }
+
+    pub fn symbol(&self, unit: Unit) -> &str {
+        match unit {
+            Unit::Currency => &self.symbol,
+            Unit::Flow => &self.flow_symbol,
+        }
+    }
+
+    /// `amount` with `symbol` on the configured side, after its sign if any.
+    pub fn place_symbol(&self, amount: &str, symbol: &str) -> String {
+        match self.placement {
+            Placement::Before => {
+                let digits = amount.trim_start_matches(['+', '-']);
+                format!("{}{symbol}{digits}", &amount[..amount.len()-digits.len()])
+            },
+            Placement::After => format!("{amount}{symbol}"),
+        }
+    }
+
+    /// `amount` with the symbol of `unit`.
+    pub fn with_symbol(&self, amount: &str, unit: Unit) -> String {
+        self.place_symbol(amount, self.symbol(unit))
+    }
+
+    /// `units` grouped by thousands, when a separator is configured.
+    pub fn units(&self, units: u64) -> String {
+        let digits = units.to_string();
+        let Some(separator) = self.thousands else {
+            return digits;
+        };
+        let mut ret = String::new();
+        for (index, c) in digits.chars().enumerate() {
+            if index > 0 && (digits.len() - index).is_multiple_of(3) {
+                ret.push(separator);
+            }
+            ret.push(c);
+        }
+        ret
+    }
+}
@@ -0,0 +1,520 @@
+use std::error;
+use std::fmt;
+use std::io::stdout;
+use std::path::Path;
+use std::rc::Rc;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use chrono::NaiveDate;
+use serde::{Serialize, Deserialize};
+
+use crate::term::*;
+use crate::interface::{stylize_amount, stylize_account, PurchaseInput, LocalCfg};
+use crate::completion::Completor;
+use crate::formatting::Unit;
+use crate::tags::{Tags, TagRef};
+use crate::accounts::{Accounts, AccountRef};
+use crate::transaction::{Transactions, Transaction, Purchase, Consumers};
+use crate::money::*;
+use crate::datetime::Date;
+use crate::regex::Regex;
+use crate::yamlrw::YamlRW;
+use crate::csvrw;
+use crate::ofx;
+use crate::qif;
+
+#[derive(Debug)]
+pub enum Error {
+    File(String, std::io::Error),
+    UnknownTag(TagRef),
+    UnknownAccount(AccountRef),
+    NoCsvMapping,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::File(ref filename, ref err) =>
+                write!(f, "Failed to read \"{filename}\": {err}"),
+            Error::UnknownTag(ref tag) =>
+                write!(f, "Import rule uses unknown tag \"{tag}\""),
+            Error::UnknownAccount(ref account) =>
+                write!(f, "Import rule uses unknown account \"{account}\""),
+            Error::NoCsvMapping =>
+                write!(f, "No csv column mapping in import.yaml"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::File(_, ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+fn default_delimiter() -> char {
+    ','
+}
+
+fn default_decimal() -> char {
+    '.'
+}
+
+fn default_qif_date_format() -> String {
+    "%m/%d/%Y".to_string()
+}
+
+/// Spending line of a bank statement.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub date: Date,
+    pub amount: CentsAmount,
+    pub desc: String,
+    /// Identifier of the line given by the bank, kept across statements.
+    pub id: Option<String>,
+}
+
+/// Columns of a bank statement, counted from 0.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CsvMapping {
+    #[serde(default = "default_delimiter")]
+    delimiter: char,
+    /// Leading lines to ignore, such as headers.
+    #[serde(default)]
+    skip: usize,
+    date: usize,
+    date_format: String,
+    amount: usize,
+    #[serde(default = "default_decimal")]
+    decimal: char,
+    #[serde(default)]
+    thousands: Option<char>,
+    /// Whether spending is written as positive amounts, statements usually write it negative.
+    #[serde(default)]
+    positive_spending: bool,
+    /// Joined with spaces when several columns are given.
+    desc: Vec<usize>,
+}
+
+impl CsvMapping {
+    fn amount(&self, text: &str) -> Option<SignedCentsAmount> {
+        let mut text: String = text.chars().filter(|c| !c.is_whitespace() && Some(*c) != self.thousands).collect();
+        let negative = text.starts_with('-') || text.ends_with('-') || text.starts_with('(') && text.ends_with(')');
+        text.retain(|c| c.is_ascii_digit() || c == self.decimal);
+        let amount = CentsAmount::parse(&text.replace(self.decimal, "."))?;
+        Some(if negative { SignedCentsAmount::negative(amount) } else { SignedCentsAmount::positive(amount) })
+    }
+
+    /// `None` for invalid records and income.
+    fn record(&self, record: &[String]) -> Option<Entry> {
+        let date = NaiveDate::parse_from_str(record.get(self.date)?.trim(), &self.date_format).ok()?;
+        let amount = self.amount(record.get(self.amount)?)?;
+        let desc = self.desc.iter().filter_map(|column| record.get(*column)).map(|x| x.split_whitespace().collect::<Vec<_>>().join(" ")).filter(|x| !x.is_empty()).collect::<Vec<_>>().join(" ");
+
+        let spent = if self.positive_spending { amount.cents() > 0 } else { amount.cents() < 0 };
+        spent.then(|| Entry{date: date.into(), amount: amount.abs(), desc, id: None})
+    }
+}
+
+/// Fields given to the imported purchases whose description matches `pattern`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rule {
+    pattern: String,
+    #[serde(default)]
+    tag: Option<TagRef>,
+    #[serde(default)]
+    buyer: Option<AccountRef>,
+    #[serde(default)]
+    consumers: Option<Consumers>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportCfg {
+    #[serde(default)]
+    csv: Option<CsvMapping>,
+    /// QIF dates follow the locale of the program that wrote them.
+    #[serde(default = "default_qif_date_format")]
+    qif_date_format: String,
+    default_tag: TagRef,
+    #[serde(default)]
+    rules: Vec<Rule>,
+}
+
+impl YamlRW for ImportCfg {}
+
+impl ImportCfg {
+    pub fn csv(&self) -> Result<&CsvMapping, Error> {
+        self.csv.as_ref().ok_or(Error::NoCsvMapping)
+    }
+
+    pub fn qif_date_format(&self) -> &str {
+        &self.qif_date_format
+    }
+}
+
+/// Import rules checked against the ledger, with compiled patterns.
+pub struct Rules {
+    rules: Vec<(Regex, Rule)>,
+    default_tag: TagRef,
+    local: AccountRef,
+}
+
+impl Rules {
+    pub fn new(cfg: &ImportCfg, tags: &Tags, accounts: &Accounts, local: &AccountRef) -> Result<Self, Box<dyn error::Error>> {
+        let check_tag = |tag: &TagRef| if tags.0.contains_key(tag) { Ok(()) } else { Err(Error::UnknownTag(tag.clone())) };
+        let check_account = |account: &AccountRef| if accounts.0.contains_key(account) { Ok(()) } else { Err(Error::UnknownAccount(account.clone())) };
+
+        check_tag(&cfg.default_tag)?;
+        let mut rules = Vec::new();
+        for rule in &cfg.rules {
+            rule.tag.iter().try_for_each(check_tag)?;
+            rule.buyer.iter().try_for_each(check_account)?;
+            rule.consumers.iter().flat_map(|x| x.0.keys()).try_for_each(check_account)?;
+            rules.push((Regex::new(&rule.pattern)?, rule.clone()));
+        }
+
+        Ok(Self{rules, default_tag: cfg.default_tag.clone(), local: local.clone()})
+    }
+
+    /// Purchase built from the first rule matching `desc`, and whether a rule matched.
+    pub fn apply(&self, entry: Entry) -> (Purchase, bool) {
+        let Entry{date, amount, desc, id} = entry;
+        let rule = self.rules.iter().find(|(regex, _)| regex.is_match(&desc)).map(|(_, rule)| rule);
+        let tag = rule.and_then(|x| x.tag.clone()).unwrap_or_else(|| self.default_tag.clone());
+        let buyer = rule.and_then(|x| x.buyer.clone()).unwrap_or_else(|| self.local.clone());
+        let consumers = rule.and_then(|x| x.consumers.clone()).unwrap_or_else(|| Consumers(BTreeMap::from([(buyer.clone(), 1)])));
+        (Purchase{date, amount, foreign: None, desc, tag, buyer, consumers, recurring: None, import_id: id}, rule.is_some())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub purchase: Purchase,
+    pub matched: bool,
+    pub duplicate: bool,
+    pub include: bool,
+    pub edited: bool,
+}
+
+type Counts = BTreeMap<(Date, CentsAmount), usize>;
+
+/// Decrements the count of `key`, returning whether it was positive.
+fn take(counts: &mut Counts, key: (Date, CentsAmount)) -> bool {
+    match counts.get_mut(&key) {
+        Some(count) if *count > 0 => {
+            *count -= 1;
+            true
+        },
+        _ => false,
+    }
+}
+
+/// Entries whose identifier was already imported are marked as duplicates.
+/// Otherwise, purchases already in `transactions` with the same date and amount are,
+/// each existing purchase accounting for one candidate. Purchases imported with an
+/// identifier only account for entries without one, as their lines are known to differ.
+pub fn candidates(entries: Vec<Entry>, rules: &Rules, transactions: &Transactions) -> Vec<Candidate> {
+    let mut ids: BTreeSet<String> = BTreeSet::new();
+    let mut all = Counts::new();
+    let mut unidentified = Counts::new();
+    for tr in transactions.vec() {
+        if let Transaction::Purchase(purchase) = tr {
+            let key = (purchase.date.clone(), purchase.amount);
+            *all.entry(key.clone()).or_default() += 1;
+            match &purchase.import_id {
+                Some(id) => {
+                    ids.insert(id.clone());
+                },
+                None => *unidentified.entry(key).or_default() += 1,
+            }
+        }
+    }
+
+    entries.into_iter().map(|entry| {
+        let key = (entry.date.clone(), entry.amount);
+        let duplicate = match &entry.id {
+            Some(id) => !ids.insert(id.clone()) || take(&mut unidentified, key),
+            None => take(&mut all, key),
+        };
+        let (purchase, matched) = rules.apply(entry);
+        Candidate{purchase, matched, duplicate, include: !duplicate, edited: false}
+    }).collect()
+}
+
+/// Statements are read as UTF-8, falling back to Latin-1 which banks still use.
+fn read_file(path: &Path) -> Result<String, Error> {
+    let bytes = std::fs::read(path).map_err(|err| Error::File(path.display().to_string(), err))?;
+    Ok(String::from_utf8(bytes).unwrap_or_else(|err| err.into_bytes().into_iter().map(char::from).collect()))
+}
+
+/// Spending entries of a CSV statement, and the number of ignored records.
+pub fn read_csv(path: &Path, mapping: &CsvMapping) -> Result<(Vec<Entry>, usize), Error> {
+    let text = read_file(path)?;
+    let records = csvrw::parse(&text, mapping.delimiter);
+    let records = records.get(mapping.skip..).unwrap_or_default();
+    let entries: Vec<_> = records.iter().filter_map(|record| mapping.record(record)).collect();
+    let ignored = records.len() - entries.len();
+    Ok((entries, ignored))
+}
+
+/// Spending entries of an OFX statement, and the number of ignored transactions.
+pub fn read_ofx(path: &Path) -> Result<(Vec<Entry>, usize), Error> {
+    Ok(ofx::parse(&read_file(path)?))
+}
+
+/// Spending entries of a QIF statement, and the number of ignored transactions.
+pub fn read_qif(path: &Path, date_format: &str) -> Result<(Vec<Entry>, usize), Error> {
+    Ok(qif::parse(&read_file(path)?, date_format))
+}
+
+pub struct ImportReview {
+    candidates: Vec<Candidate>,
+    ignored: usize,
+    tags: Tags,
+    accounts: Accounts,
+    cfg: Rc<RefCell<LocalCfg>>,
+    selection: usize,
+    input: Option<PurchaseInput>,
+    committed: bool,
+}
+
+impl ImportReview {
+    pub fn new(candidates: Vec<Candidate>, ignored: usize, tags: Tags, accounts: Accounts, cfg: Rc<RefCell<LocalCfg>>) -> Self {
+        Self{candidates, ignored, tags, accounts, cfg, selection: 0, input: None, committed: false}
+    }
+
+    pub fn committed(&self) -> bool {
+        self.committed
+    }
+
+    pub fn included(self) -> Vec<Purchase> {
+        self.candidates.into_iter().filter(|x| x.include).map(|x| x.purchase).collect()
+    }
+
+    fn edit_selected(&mut self) {
+        let Some(candidate) = self.candidates.get(self.selection) else {
+            return;
+        };
+        // Statements are in the currency of the ledger.
+        let currency_completor = Completor::new(Vec::new());
+        let desc_completor = Completor::new(Vec::new());
+        let tag_completor = Completor::new(self.tags.0.keys().cloned().collect());
+        let account_completor = Completor::new(self.accounts.0.keys().cloned().collect());
+
+        self.input = Some(PurchaseInput::from_purchase(&candidate.purchase, currency_completor, desc_completor, tag_completor, account_completor, Rc::clone(&self.cfg)));
+    }
+
+    fn input_box(&self, element_box: TermBox) -> TermBox {
+        TermBox{left: element_box.left, right: element_box.right, top: element_box.top+3, bottom: element_box.bottom}
+    }
+
+    fn display_candidate(&self, candidate: &Candidate, element_box: TermBox, active: bool) -> crossterm::Result<()> {
+        use crossterm::{
+            queue,
+            style::{PrintStyledContent, Color},
+        };
+
+        let cfg = self.cfg.borrow();
+        let format = cfg.format();
+        let [check_width, _, date_width, _, tag_width, _, desc_width, _, buyer_width, _, amount_width, _, status_width] = subdiv_const_flex(element_box.width(), [(3, 0), (1, 0), (format.date_width(), 0), (1, 0), (6, 2), (1, 0), (6, 6), (1, 0), (6, 1), (1, 0), (10, 0), (1, 0), (9, 0)]);
+
+        let purchase = &candidate.purchase;
+        let check = if candidate.include { "[x]" } else { "[ ]" };
+        let status = match (candidate.duplicate, candidate.edited, candidate.matched) {
+            (true, _, _) => "duplicate",
+            (false, true, _) => "edited",
+            (false, false, false) => "no rule",
+            (false, false, true) => "",
+        };
+        let space = simple_stylize(" ", Color::Reset, true, active);
+        let short = purchase.buyer.chars().count() > buyer_width;
+        let buyer_len = if short { 1 } else { purchase.buyer.chars().count() };
+
+        element_box.begin().goto()?;
+        queue!(stdout(),
+            PrintStyledContent(simple_stylize(truncate_align_left(check, check_width), Color::Reset, true, active)),
+            PrintStyledContent(space),
+            PrintStyledContent(simple_stylize(truncate_align_left(&format.date(&purchase.date), date_width), Color::Reset, true, active)),
+            PrintStyledContent(space),
+            PrintStyledContent(simple_stylize(truncate_align_left(&purchase.tag, tag_width), Color::Reset, true, active)),
+            PrintStyledContent(space),
+            PrintStyledContent(simple_stylize(truncate_align_left(&purchase.desc, desc_width), Color::Reset, true, active)),
+            PrintStyledContent(space),
+            PrintStyledContent(stylize_account(purchase.buyer.clone(), short, active, &self.accounts)),
+            PrintStyledContent(simple_stylize(" ".repeat(buyer_width - buyer_len), Color::Reset, true, active)),
+            PrintStyledContent(space),
+            PrintStyledContent(stylize_amount(SignedCentsAmount::negative(purchase.amount), Unit::Currency, amount_width, active, format)),
+            PrintStyledContent(space),
+            PrintStyledContent(simple_stylize(truncate_align_left(status, status_width), Color::Yellow, true, active)),
+        )?;
+
+        Ok(())
+    }
+}
+
+impl TermElement for ImportReview {
+    fn display(&self, element_box: TermBox, _active: bool) -> crossterm::Result<()> {
+        use crossterm::{
+            queue,
+            style::{PrintStyledContent, Color},
+        };
+
+        let included = self.candidates.iter().filter(|x| x.include).count();
+        let title = format!("Import {} of {} transactions ({} records ignored)", included, self.candidates.len(), self.ignored);
+        TermPos::new(element_box.left, element_box.top).goto()?;
+        queue!(stdout(), PrintStyledContent(simple_stylize(truncate_align_left(&title, element_box.width()), Color::Reset, true, false)))?;
+        let help = match self.input {
+            Some(_) => "Tab: next field, Enter: save, Esc: discard changes",
+            None => "Space: include/exclude, e: edit, Enter: import, Esc: cancel",
+        };
+        TermPos::new(element_box.left, element_box.top+1).goto()?;
+        queue!(stdout(), PrintStyledContent(simple_stylize(truncate_align_left(help, element_box.width()), Color::Reset, false, false)))?;
+
+        if let Some(input) = &self.input {
+            return input.display(self.input_box(element_box), true);
+        }
+
+        let list_top = element_box.top + 3;
+        let list_height = element_box.bottom.saturating_sub(list_top);
+        if list_height == 0 || element_box.width() < 80 {
+            return Ok(());
+        }
+
+        let begin = self.selection.saturating_sub(list_height / 2).min(self.candidates.len().saturating_sub(list_height));
+        for (row, (index, candidate)) in self.candidates.iter().enumerate().skip(begin).take(list_height).enumerate() {
+            let row_box = TermBox{left: element_box.left, right: element_box.right, top: list_top + row, bottom: list_top + row + 1};
+            self.display_candidate(candidate, row_box, index == self.selection)?;
+        }
+
+        Ok(())
+    }
+
+    fn popup(&self, element_box: TermBox, window_box: TermBox) -> crossterm::Result<()> {
+        match &self.input {
+            Some(input) => input.popup(self.input_box(element_box), window_box),
+            None => Ok(()),
+        }
+    }
+
+    fn set_cursor(&self, element_box: TermBox, window_box: TermBox) -> crossterm::Result<()> {
+        use crossterm::{queue, cursor};
+
+        match &self.input {
+            Some(input) => input.set_cursor(self.input_box(element_box), window_box),
+            None => queue!(stdout(), cursor::Hide),
+        }
+    }
+
+    fn input(&mut self, event: InputEvent) -> Option<InputEvent> {
+        use InputEvent::*;
+
+        if let Some(input) = &mut self.input {
+            match input.input(event) {
+                Some(Tab | Enter) => {
+                    let candidate = &mut self.candidates[self.selection];
+                    candidate.purchase = input.clone().into();
+                    candidate.edited = true;
+                    candidate.include = true;
+                    self.input = None;
+                },
+                Some(Esc) => self.input = None,
+                _ => (),
+            }
+            return None;
+        }
+
+        match event {
+            Up => {
+                self.selection = self.selection.saturating_sub(1);
+                None
+            },
+            Down => {
+                self.selection = usize::min(self.selection + 1, self.candidates.len().saturating_sub(1));
+                None
+            },
+            Char(' ') => {
+                if let Some(candidate) = self.candidates.get_mut(self.selection) {
+                    candidate.include = !candidate.include;
+                }
+                None
+            },
+            Char('e') => {
+                self.edit_selected();
+                None
+            },
+            Enter => {
+                self.committed = true;
+                Some(Enter)
+            },
+            Esc => Some(Esc),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OFX: &str = "<OFX><BANKTRANLIST>
+<STMTTRN><DTPOSTED>20260302<TRNAMT>-12.50<FITID>A1<NAME>Bakery</STMTTRN>
+<STMTTRN><DTPOSTED>20260302<TRNAMT>-12.50<FITID>A2<NAME>Bakery</STMTTRN>
+<STMTTRN><DTPOSTED>20260304<TRNAMT>-30.00<FITID>A3<NAME>Pharmacy</STMTTRN>
+</BANKTRANLIST></OFX>";
+
+    const QIF: &str = "!Type:Bank\nD3/2'26\nT-12.50\nPBakery\n^\nD3/2'26\nT-12.50\nPBakery\n^\nD3/4'26\nT-30.00\nPPharmacy\n^\n";
+
+    fn rules() -> Rules {
+        let cfg: ImportCfg = serde_yaml::from_str("default_tag: food\nrules:\n- pattern: Pharmacy\n  tag: health\n").unwrap();
+        let tags: Tags = serde_yaml::from_str("food:\n  dur: 7\n  parent: null\nhealth:\n  dur: 30\n  parent: null\n").unwrap();
+        let accounts: Accounts = serde_yaml::from_str("alice:\n  color: e06c75\n").unwrap();
+        Rules::new(&cfg, &tags, &accounts, &"alice".to_string()).unwrap()
+    }
+
+    /// Adds the included candidates to `transactions`, returning how many they were.
+    fn import(entries: Vec<Entry>, transactions: &mut Transactions) -> usize {
+        let included: Vec<Purchase> = candidates(entries, &rules(), transactions).into_iter().filter(|x| x.include).map(|x| x.purchase).collect();
+        let count = included.len();
+        for purchase in included {
+            transactions.add(Transaction::Purchase(purchase));
+        }
+        count
+    }
+
+    #[test]
+    fn rules_applied() {
+        let tags: Vec<(String, bool)> = candidates(ofx::parse(OFX).0, &rules(), &Transactions::new()).into_iter().map(|x| (x.purchase.tag, x.matched)).collect();
+        assert_eq!(tags, [("food".to_string(), false), ("food".to_string(), false), ("health".to_string(), true)]);
+    }
+
+    #[test]
+    fn same_statement_twice() {
+        let mut transactions = Transactions::new();
+        assert_eq!(import(ofx::parse(OFX).0, &mut transactions), 3);
+        assert_eq!(import(ofx::parse(OFX).0, &mut transactions), 0);
+
+        // Without identifiers, each existing purchase of the same date and amount accounts for one line.
+        let mut transactions = Transactions::new();
+        assert_eq!(import(qif::parse(QIF, "%m/%d/%Y").0, &mut transactions), 3);
+        assert_eq!(import(qif::parse(QIF, "%m/%d/%Y").0, &mut transactions), 0);
+        assert_eq!(transactions.vec().len(), 3);
+    }
+
+    #[test]
+    fn identified_lines_differ() {
+        // Lines imported without identifiers match the same ones given with identifiers later.
+        let mut transactions = Transactions::new();
+        assert_eq!(import(qif::parse(QIF, "%m/%d/%Y").0, &mut transactions), 3);
+        assert_eq!(import(ofx::parse(OFX).0, &mut transactions), 0);
+
+        // Lines with new identifiers are new, even with the date and amount of imported ones.
+        let mut transactions = Transactions::new();
+        assert_eq!(import(ofx::parse(OFX).0, &mut transactions), 3);
+        assert_eq!(import(ofx::parse(&OFX.replace("A2", "A4")).0, &mut transactions), 1);
+        assert_eq!(import(ofx::parse(OFX).0, &mut transactions), 0);
+        assert_eq!(import(qif::parse(QIF, "%m/%d/%Y").0, &mut transactions), 0);
+    }
+}
@@ -6,21 +6,54 @@ use serde::{Serialize, Deserialize};
 
 use crate::term::*;
 use crate::money::*;
+use crate::calc;
 use crate::datetime::Date;
 use crate::completion::Completor;
-use crate::transaction::{Transactions, Transaction, Purchase, Consumers};
-use crate::tags::Tags;
+use crate::transaction::{Transactions, Transaction, Purchase, Transfer, Consumers};
+use crate::tags::{Tags, TagRef};
+use std::collections::{BTreeMap, BTreeSet};
 use crate::accounts::*;
+use crate::color::RGBColor;
 use crate::yamlrw::YamlRW;
+use crate::ledger::*;
+use crate::chart::{self, ChartOptions};
+use crate::recurring;
+use crate::budget::{self, BudgetStatus, Level};
+use crate::report::{Report, Period, Totals};
+use crate::formatting::{Formatting, Placement, Unit};
+use crate::currency::{self, Rates, ForeignAmount};
+use std::path::PathBuf;
 
 #[derive(Clone)]
 pub struct DateInput {
     date: Date,
+    /// Date being typed, `date` following it whenever it parses.
+    text: String,
+    /// Date before typing started, restored when it is cancelled.
+    before: Date,
+    cfg: Rc<RefCell<LocalCfg>>,
 }
 
 impl DateInput {
-    pub fn new(date: Date) -> Self {
-        Self{date}
+    pub fn new(date: Date, cfg: Rc<RefCell<LocalCfg>>) -> Self {
+        Self{date: date.clone(), text: String::new(), before: date, cfg}
+    }
+
+    /// Accepts `-N` and `+N` for N days from today besides the configured date format.
+    fn parse(&self, text: &str) -> Option<Date> {
+        let relative = |digits: &str, sign: i64| digits.parse::<i64>().ok().filter(|_| digits.chars().all(|c| c.is_ascii_digit())).and_then(|days| Date::today().plus_days(sign * days));
+        if let Some(digits) = text.strip_prefix('-') {
+            relative(digits, -1)
+        } else if let Some(digits) = text.strip_prefix('+') {
+            relative(digits, 1)
+        } else {
+            self.cfg.borrow().format().parse_date(text)
+        }
+    }
+
+    fn set_date(&mut self, date: Date) {
+        self.date = date;
+        self.text.clear();
     }
 }
 
@@ -31,7 +64,16 @@ impl TermElement for DateInput {
             style::{PrintStyledContent, Stylize}
         };
 
-        let mut tmp = self.date.to_string().bold();
+        let format = self.cfg.borrow().format().clone();
+        let mut tmp = if self.text.is_empty() {
+            format.date(&self.date).bold()
+        } else {
+            let text = format!("{: <width$}", self.text, width = format.date_width());
+            match self.parse(&self.text) {
+                Some(_) => text.bold(),
+                None => text.bold().red(),
+            }
+        };
         if active {
             tmp = tmp.reverse();
         }
@@ -41,34 +83,112 @@ impl TermElement for DateInput {
         Ok(())
     }
 
-    fn popup(&self, _element_box: TermBox, _window_box: TermBox) -> crossterm::Result<()> {
+    /// Calendar of the month of the date.
+    fn popup(&self, element_box: TermBox, window_box: TermBox) -> crossterm::Result<()> {
+        use chrono::Datelike;
+        use crossterm::{
+            queue,
+            style::{PrintStyledContent, Stylize}
+        };
+
+        let offset = self.date.first_of_month().naive().weekday().num_days_from_monday() as usize;
+        let days = self.date.days_in_month() as usize;
+        let selected = self.date.naive().day() as usize;
+        let weeks = (offset + days).div_ceil(7);
+
+        for (lig, row) in ((element_box.top+1)..(window_box.bottom)).zip(0..weeks+2) {
+            TermPos::new(element_box.left, lig).goto()?;
+            match row {
+                0 => queue!(stdout(), PrintStyledContent(format!("{: ^20}", self.date.naive().format("%B %Y").to_string()).bold()))?,
+                1 => queue!(stdout(), PrintStyledContent("Mo Tu We Th Fr Sa Su".bold()))?,
+                _ => {
+                    for column in 0..7 {
+                        let cell = (row - 2) * 7 + column + 1;
+                        let day = (cell > offset && cell - offset <= days).then(|| cell - offset);
+                        let text = day.map_or("  ".to_string(), |day| format!("{day: >2}")).stylize();
+                        let text = if day == Some(selected) { text.reverse() } else { text };
+                        queue!(stdout(), PrintStyledContent(text))?;
+                        if column < 6 {
+                            queue!(stdout(), PrintStyledContent(" ".stylize()))?;
+                        }
+                    }
+                },
+            }
+        }
         Ok(())
     }
 
-    fn set_cursor(&self, _element_box: TermBox, _window_box: TermBox) -> crossterm::Result<()> {
+    fn set_cursor(&self, element_box: TermBox, _window_box: TermBox) -> crossterm::Result<()> {
         use crossterm::{queue, cursor};
-        queue!(stdout(), cursor::Hide)
+        if self.text.is_empty() {
+            queue!(stdout(), cursor::Hide)
+        } else {
+            TermPos::new(element_box.left + self.text.chars().count(), element_box.top).goto()?;
+            queue!(stdout(), cursor::Show, cursor::SetCursorStyle::BlinkingBar)
+        }
     }
 
     fn input(&mut self, event: InputEvent) -> Option<InputEvent> {
         use InputEvent::*;
         match event {
             Down | Right => {
-                self.date = self.date.succ();
+                self.set_date(self.date.succ());
                 None
             },
             Up | Left => {
-                self.date = self.date.pred();
+                self.set_date(self.date.pred());
                 None
             },
-            _ => Some(event),
+            PageDown => {
+                self.set_date(self.date.plus_months(1));
+                None
+            },
+            PageUp => {
+                self.set_date(self.date.plus_months(-1));
+                None
+            },
+            Char('t') if self.text.is_empty() => {
+                self.set_date(Date::today());
+                None
+            },
+            Char('y') if self.text.is_empty() => {
+                self.set_date(Date::today().pred());
+                None
+            },
+            Char(c) => {
+                if self.text.is_empty() {
+                    self.before = self.date.clone();
+                }
+                self.text.push(c);
+                if let Some(date) = self.parse(&self.text) {
+                    self.date = date;
+                }
+                None
+            },
+            Backspace if !self.text.is_empty() => {
+                let _ = self.text.pop();
+                if self.text.is_empty() {
+                    self.date = self.before.clone();
+                } else if let Some(date) = self.parse(&self.text) {
+                    self.date = date;
+                }
+                None
+            },
+            Esc if !self.text.is_empty() => {
+                self.set_date(self.before.clone());
+                None
+            },
+            _ => {
+                self.text.clear();
+                Some(event)
+            },
         }
     }
 }
 
 impl fmt::Display for DateInput {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.date.to_string())
+        write!(f, "{}", self.date)
     }
 }
 
@@ -80,26 +200,72 @@ impl From<DateInput> for Date {
 
 
 
+/// Amount typed as a decimal number or as an expression of some, see `calc::evaluate`.
 #[derive(Clone)]
 pub struct AmountInput {
-    cents: u64,
-    separator_dist: Option<usize>,
+    text: String,
+    currency: String,
+    cfg: Rc<RefCell<LocalCfg>>,
 }
 
+const AMOUNT_INPUT_MAX_LEN: usize = 40;
+
 impl AmountInput {
-    pub fn new() -> Self {
-        Self {
-            cents: 0,
-            separator_dist: None,
-        }
+    pub fn new(cfg: Rc<RefCell<LocalCfg>>) -> Self {
+        let currency = cfg.borrow().format().symbol.clone();
+        Self{text: String::new(), currency, cfg}
+    }
+
+    pub fn set(&mut self, amount: CentsAmount) {
+        self.text = match amount.cents() % 100 {
+            0 => (amount.cents() / 100).to_string(),
+            cents => format!("{}.{cents:02}", amount.cents() / 100),
+        };
+    }
+
+    pub fn set_currency(&mut self, currency: String) {
+        self.currency = currency;
+    }
+
+    pub fn amount(&self) -> Option<CentsAmount> {
+        calc::evaluate(&self.text)
     }
 
     pub fn valid(&self) -> bool {
-        self.cents != 0
+        self.amount().is_some_and(|amount| amount.cents() != 0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// The typed text, with the configured decimal mark and grouped units for plain numbers.
+    fn shown_text(&self) -> String {
+        if calc::is_expression(&self.text) {
+            return self.text.clone();
+        }
+        let cfg = self.cfg.borrow();
+        let format = cfg.format();
+        let (units, decimals) = match self.text.split_once(['.', ',']) {
+            Some((units, decimals)) => (units, Some(decimals)),
+            None => (self.text.as_str(), None),
+        };
+        let mut ret = match units.parse::<u64>() {
+            Ok(value) if units.chars().all(|c| c.is_ascii_digit()) => format.units(value),
+            _ => units.to_string(),
+        };
+        if let Some(decimals) = decimals {
+            ret.push(format.decimal);
+            ret += decimals;
+        }
+        ret
     }
 
-    pub fn len(&self) -> usize {
-        format!("{self}").len()
+    fn prefix_len(&self) -> usize {
+        match self.cfg.borrow().format().placement {
+            Placement::Before => self.currency.chars().count() + 1,
+            Placement::After => 0,
+        }
     }
 }
 
@@ -111,6 +277,9 @@ impl TermElement for AmountInput {
         };
 
         let mut tmp = format!("{self}").bold();
+        if !self.text.is_empty() && self.amount().is_none() {
+            tmp = tmp.red();
+        }
         if active {
             tmp = tmp.reverse();
         }
@@ -127,7 +296,7 @@ impl TermElement for AmountInput {
 
     fn set_cursor(&self, element_box: TermBox, _window_box: TermBox) -> crossterm::Result<()> {
         use crossterm::{queue, cursor};
-        TermPos::new(element_box.left + self.len() - 2, element_box.top).goto()?;
+        TermPos::new(element_box.left + self.prefix_len() + self.shown_text().chars().count(), element_box.top).goto()?;
         queue!(stdout(), cursor::Show, cursor::SetCursorStyle::BlinkingBar)
     }
 
@@ -136,58 +305,14 @@ impl TermElement for AmountInput {
 
         match event {
             Backspace => {
-                match self.separator_dist {
-                    None => {
-                        self.cents = self.cents / 1000 * 100;
-                    },
-                    Some(0) => {
-                        self.separator_dist = None;
-                    },
-                    Some(1) => {
-                        self.cents = self.cents / 100 * 100;
-                        self.separator_dist = Some(0);
-                    },
-                    Some(2) => {
-                        self.cents = self.cents / 10 * 10;
-                        self.separator_dist = Some(1);
-                    },
-                    _ => unreachable!(),
-                }
+                let _ = self.text.pop();
                 None
             }
             Char(c) => {
-                if let Some(Ok(val)) = c.to_digit(10).map(u64::try_from) {
-                    match self.separator_dist {
-                        None => {
-                            // avoid overflow
-                            if self.cents < 100_000_000_00 {
-                                self.cents = self.cents * 10 + val * 100;
-                            }
-                            None
-                        },
-                        Some(0) => {
-                            self.cents += val * 10;
-                            self.separator_dist = Some(1);
-                            None
-                        },
-                        Some(1) => {
-                            self.cents += val;
-                            self.separator_dist = Some(2);
-                            Some(Tab)
-                        },
-                        Some(2) => Some(Tab),
-                        _ => unreachable!(),
-                    }
-                } else if c == '.' || c == ',' {
-                    if self.separator_dist == None {
-                        self.separator_dist = Some(0);
-                        None
-                    } else {
-                        Some(Tab)
-                    }
-                } else {
-                    None
+                if (c.is_ascii_digit() || ".,+-*/()".contains(c)) && self.text.len() < AMOUNT_INPUT_MAX_LEN {
+                    self.text.push(c);
                 }
+                None
             },
             _ => Some(event),
         }
@@ -196,21 +321,180 @@ impl TermElement for AmountInput {
 
 impl fmt::Display for AmountInput {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", self.cents / 100)?;
-        match self.separator_dist {
-            None => (),
-            Some(0) => write!(f, ".")?,
-            Some(1) => write!(f, ".{}", (self.cents / 10) % 10)?,
-            Some(2) => write!(f, ".{:02}", self.cents % 100)?,
-            _ => unreachable!(),
+        let text = self.shown_text();
+        let cfg = self.cfg.borrow();
+        let text = match (calc::is_expression(&self.text), self.amount()) {
+            (false, _) => text,
+            (true, Some(amount)) => format!("{text} = {}", amount.as_string_exact(cfg.format().marks(true))),
+            (true, None) => format!("{text} = ?"),
+        };
+        match cfg.format().placement {
+            Placement::Before => write!(f, "{} {text}", self.currency),
+            Placement::After => write!(f, "{text} {}", self.currency),
         }
-        write!(f, " €")
     }
 }
 
 impl From<AmountInput> for CentsAmount {
     fn from(amount: AmountInput) -> CentsAmount {
-        CentsAmount::new(amount.cents)
+        amount.amount().unwrap_or(CentsAmount::new(0))
+    }
+}
+
+
+
+#[derive(Clone)]
+pub struct NumberInput {
+    value: usize,
+    unit: &'static str,
+}
+
+impl NumberInput {
+    pub fn new(value: usize, unit: &'static str) -> Self {
+        Self{value, unit}
+    }
+
+    pub fn valid(&self) -> bool {
+        self.value != 0
+    }
+
+    pub fn get(&self) -> usize {
+        self.value
+    }
+
+    pub fn set(&mut self, value: usize) {
+        self.value = value;
+    }
+}
+
+impl TermElement for NumberInput {
+    fn display(&self, element_box: TermBox, active: bool) -> crossterm::Result<()> {
+        use crossterm::{
+            queue,
+            style::{PrintStyledContent, Stylize}
+        };
+
+        let mut tmp = format!("{self}").bold();
+        if active {
+            tmp = tmp.reverse();
+        }
+
+        element_box.begin().goto()?;
+        queue!(stdout(), PrintStyledContent(tmp))?;
+
+        Ok(())
+    }
+
+    fn popup(&self, _element_box: TermBox, _window_box: TermBox) -> crossterm::Result<()> {
+        Ok(())
+    }
+
+    fn set_cursor(&self, element_box: TermBox, _window_box: TermBox) -> crossterm::Result<()> {
+        use crossterm::{queue, cursor};
+        TermPos::new(element_box.left + self.value.to_string().len(), element_box.top).goto()?;
+        queue!(stdout(), cursor::Show, cursor::SetCursorStyle::BlinkingBar)
+    }
+
+    fn input(&mut self, event: InputEvent) -> Option<InputEvent> {
+        use InputEvent::*;
+
+        match event {
+            Backspace => {
+                self.value /= 10;
+                None
+            },
+            Up | Right => {
+                self.value = self.value.saturating_add(1);
+                None
+            },
+            Down | Left => {
+                self.value = self.value.saturating_sub(1);
+                None
+            },
+            Char(c) => {
+                if let Some(val) = c.to_digit(10) {
+                    // avoid overflow
+                    if self.value < 100_000 {
+                        self.value = self.value * 10 + val as usize;
+                    }
+                }
+                None
+            },
+            _ => Some(event),
+        }
+    }
+}
+
+impl fmt::Display for NumberInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.value, self.unit)
+    }
+}
+
+
+
+#[derive(Clone)]
+pub struct ColorInput {
+    text: String,
+}
+
+impl ColorInput {
+    pub fn new(color: Option<RGBColor>) -> Self {
+        Self{text: color.map(|x| x.to_string()).unwrap_or_default()}
+    }
+
+    pub fn get(&self) -> Option<RGBColor> {
+        RGBColor::from_hex(&self.text)
+    }
+}
+
+impl TermElement for ColorInput {
+    fn display(&self, element_box: TermBox, active: bool) -> crossterm::Result<()> {
+        use crossterm::{
+            queue,
+            style::{Print, PrintStyledContent, Stylize}
+        };
+
+        let mut tmp = format!("#{: <6}", self.text).bold();
+        if active {
+            tmp = tmp.reverse();
+        }
+
+        element_box.begin().goto()?;
+        queue!(stdout(), PrintStyledContent(tmp))?;
+        if let Some(color) = self.get() {
+            queue!(stdout(), Print(" "), PrintStyledContent("██".with(color.into())))?;
+        }
+
+        Ok(())
+    }
+
+    fn popup(&self, _element_box: TermBox, _window_box: TermBox) -> crossterm::Result<()> {
+        Ok(())
+    }
+
+    fn set_cursor(&self, element_box: TermBox, _window_box: TermBox) -> crossterm::Result<()> {
+        use crossterm::{queue, cursor};
+        TermPos::new(element_box.left + 1 + self.text.len(), element_box.top).goto()?;
+        queue!(stdout(), cursor::Show, cursor::SetCursorStyle::BlinkingBar)
+    }
+
+    fn input(&mut self, event: InputEvent) -> Option<InputEvent> {
+        use InputEvent::*;
+
+        match event {
+            Backspace => {
+                let _ = self.text.pop();
+                None
+            },
+            Char(c) => {
+                if c.is_ascii_hexdigit() && self.text.len() < 6 {
+                    self.text.push(c.to_ascii_lowercase());
+                }
+                None
+            },
+            _ => Some(event),
+        }
     }
 }
 
@@ -253,6 +537,12 @@ impl CompletorInput {
         self.text.clone()
     }
 
+    pub fn set(&mut self, text: String) {
+        self.text = text;
+        self.compl.update(&self.text);
+        self.selection = None;
+    }
+
     fn exit(&mut self) {
         if self.strict && !self.is_empty() {
             self.text = self.compl.matches()[self.selection.unwrap_or(0)].clone();
@@ -367,7 +657,7 @@ impl From<CompletorInput> for String {
 #[derive(Clone)]
 pub struct UsersInput {
     new_user: CompletorInput,
-    users: Vec<String>,
+    users: Vec<(String, usize)>,
     selection: Option<usize>,
 }
 
@@ -380,9 +670,29 @@ impl UsersInput {
         !self.users.is_empty()
     }
 
-    pub fn add_user(&mut self, user: String) {
-        if !self.users.contains(&user) {
-            self.users.push(user);
+    pub fn is_empty(&self) -> bool {
+        self.users.is_empty() && self.new_user.is_empty()
+    }
+
+    pub fn add_user(&mut self, user: String, weight: usize) {
+        if !self.users.iter().any(|(x, _)| x == &user) {
+            self.users.push((user, weight));
+        }
+    }
+
+    pub fn set(&mut self, consumers: &Consumers) {
+        self.users = consumers.0.iter().map(|(user, weight)| (user.clone(), *weight)).collect();
+        self.selection = None;
+    }
+
+    fn change_weight(&mut self, incr: bool) {
+        if let Some(x) = self.selection {
+            let weight = &mut self.users[x].1;
+            if incr {
+                *weight += 1;
+            } else if *weight > 1 {
+                *weight -= 1;
+            }
         }
     }
 
@@ -411,7 +721,7 @@ impl UsersInput {
     fn validate_new_user(&mut self) {
         if !self.new_user.is_empty() {
             self.new_user.exit();
-            self.add_user(self.new_user.get());
+            self.add_user(self.new_user.get(), 1);
             self.new_user.clear();
         }
     }
@@ -432,8 +742,9 @@ impl TermElement for UsersInput {
 
         TermPos::new(element_box.left+self.new_user.display_len(), element_box.top).goto()?;
 
-        for (n, user) in self.users.iter().enumerate() {
-            let mut tmp: crossterm::style::StyledContent<String> = format!("{}", user).bold();
+        for (n, (user, weight)) in self.users.iter().enumerate() {
+            let text = if *weight == 1 { user.clone() } else { format!("{user}:{weight}") };
+            let mut tmp: crossterm::style::StyledContent<String> = text.bold();
             if active && self.selection == Some(n) {
                 tmp = tmp.reverse();
             }
@@ -506,6 +817,14 @@ impl TermElement for UsersInput {
                                 self.del_user();
                                 None
                             },
+                            Char('+') => {
+                                self.change_weight(true);
+                                None
+                            },
+                            Char('-') => {
+                                self.change_weight(false);
+                                None
+                            },
                             Tab | Enter | BackTab => {
                                 self.exit();
                                 Some(event)
@@ -521,7 +840,7 @@ impl TermElement for UsersInput {
 
 impl From<UsersInput> for Consumers {
     fn from(users: UsersInput) -> Consumers {
-        Consumers(users.users.into_iter().map(|user| (user, 1)).collect())
+        Consumers(users.users.into_iter().collect())
     }
 }
 
@@ -531,6 +850,7 @@ impl From<UsersInput> for Consumers {
 enum PurchaseInputFocus {
     Date,
     Amount,
+    Currency,
     Desc,
     Tag,
     Buyer,
@@ -546,7 +866,8 @@ impl PurchaseInputFocus {
         use PurchaseInputFocus::*;
         *self = match self {
             Date => Amount,
-            Amount => Desc,
+            Amount => Currency,
+            Currency => Desc,
             Desc => Tag,
             Tag => Buyer,
             Buyer => Consumers,
@@ -559,7 +880,8 @@ impl PurchaseInputFocus {
         *self = match self {
             Date => Consumers,
             Amount => Date,
-            Desc => Amount,
+            Currency => Amount,
+            Desc => Currency,
             Tag => Desc,
             Buyer => Tag,
             Consumers => Buyer,
@@ -571,13 +893,9 @@ impl PurchaseInputFocus {
         self == &Consumers
     }
 
-    pub fn all() -> [Self; 6] {
+    pub fn all() -> [Self; 7] {
         use PurchaseInputFocus::*;
-        [Date, Amount, Desc, Tag, Buyer, Consumers]
-    }
-
-    pub fn count() -> usize {
-        Self::all().len()
+        [Date, Amount, Currency, Desc, Tag, Buyer, Consumers]
     }
 }
 
@@ -586,27 +904,88 @@ pub struct PurchaseInput {
     focus: PurchaseInputFocus,
     date: DateInput,
     amount: AmountInput,
+    /// Empty for the ledger currency.
+    currency: CompletorInput,
     desc: CompletorInput,
     tag: CompletorInput,
     buyer: CompletorInput,
     consumers: UsersInput,
+    recurring: Option<String>,
+    import_id: Option<String>,
+    /// Latest purchase of each description, used to fill the other fields once one is chosen.
+    templates: BTreeMap<String, Purchase>,
+    cfg: Rc<RefCell<LocalCfg>>,
 }
 
 impl PurchaseInput {
-    pub fn new(date: Date, desc_completor: Completor, tag_completor: Completor, account_completor: Completor) -> Self {
+    pub fn new(date: Date, currency_completor: Completor, desc_completor: Completor, tag_completor: Completor, account_completor: Completor, cfg: Rc<RefCell<LocalCfg>>) -> Self {
         Self{
             focus: PurchaseInputFocus::new(),
-            date: DateInput::new(date),
-            amount: AmountInput::new(),
+            date: DateInput::new(date, Rc::clone(&cfg)),
+            amount: AmountInput::new(Rc::clone(&cfg)),
+            currency: CompletorInput::new('(', ')', true, currency_completor),
             desc: CompletorInput::new('"', '"', false, desc_completor),
             tag: CompletorInput::new('<', '>', true, tag_completor),
             buyer: CompletorInput::new('[', ']', true, account_completor.clone()),
             consumers: UsersInput::new(account_completor),
+            recurring: None,
+            import_id: None,
+            templates: BTreeMap::new(),
+            cfg,
+        }
+    }
+
+    pub fn set_templates(&mut self, templates: BTreeMap<String, Purchase>) {
+        self.templates = templates;
+    }
+
+    /// Fills the fields left empty from the template of the description, if any.
+    fn prefill(&mut self) {
+        let Some(template) = self.templates.get(&self.desc.get()) else {
+            return;
+        };
+        if self.amount.is_empty() && self.currency.is_empty() {
+            match &template.foreign {
+                Some(foreign) => {
+                    self.amount.set(foreign.amount);
+                    self.amount.set_currency(foreign.currency.clone());
+                    self.currency.set(foreign.currency.clone());
+                },
+                None => self.amount.set(template.amount),
+            }
+        }
+        if self.tag.is_empty() {
+            self.tag.set(template.tag.clone());
+        }
+        if self.buyer.is_empty() {
+            self.buyer.set(template.buyer.clone());
+        }
+        if self.consumers.is_empty() {
+            self.consumers.set(&template.consumers);
+        }
+    }
+
+    pub fn from_purchase(purchase: &Purchase, currency_completor: Completor, desc_completor: Completor, tag_completor: Completor, account_completor: Completor, cfg: Rc<RefCell<LocalCfg>>) -> Self {
+        let mut ret = Self::new(purchase.date.clone(), currency_completor, desc_completor, tag_completor, account_completor, cfg);
+        match &purchase.foreign {
+            Some(foreign) => {
+                ret.amount.set(foreign.amount);
+                ret.amount.set_currency(foreign.currency.clone());
+                ret.currency.set(foreign.currency.clone());
+            },
+            None => ret.amount.set(purchase.amount),
         }
+        ret.desc.set(purchase.desc.clone());
+        ret.tag.set(purchase.tag.clone());
+        ret.buyer.set(purchase.buyer.clone());
+        ret.consumers.set(&purchase.consumers);
+        ret.recurring = purchase.recurring.clone();
+        ret.import_id = purchase.import_id.clone();
+        ret
     }
 
     pub fn valid(&self) -> bool {
-        self.amount.valid() && self.desc.valid() && self.tag.valid() && self.buyer.valid() && self.consumers.valid()
+        self.amount.valid() && (self.currency.is_empty() || self.currency.valid()) && self.desc.valid() && self.tag.valid() && self.buyer.valid() && self.consumers.valid()
     }
 
     fn child(&self, index: PurchaseInputFocus) -> &dyn TermElement {
@@ -615,6 +994,7 @@ impl PurchaseInput {
         match index {
             Date      =>      &self.date,
             Amount    =>    &self.amount,
+            Currency  =>  &self.currency,
             Desc      =>      &self.desc,
             Tag       =>       &self.tag,
             Buyer     =>     &self.buyer,
@@ -622,16 +1002,18 @@ impl PurchaseInput {
         }
     }
 
+    #[allow(clippy::identity_op)]
     fn child_box(&self, index: PurchaseInputFocus, element_box: TermBox) -> TermBox {
         use PurchaseInputFocus::*;
 
         match index {
             Date      => TermBox{left: element_box.left, right: element_box.right, top: element_box.top+0, bottom: element_box.top+1},
             Amount    => TermBox{left: element_box.left, right: element_box.right, top: element_box.top+1, bottom: element_box.top+2},
-            Desc      => TermBox{left: element_box.left, right: element_box.right, top: element_box.top+2, bottom: element_box.top+3},
-            Tag       => TermBox{left: element_box.left, right: element_box.right, top: element_box.top+3, bottom: element_box.top+4},
-            Buyer     => TermBox{left: element_box.left, right: element_box.right, top: element_box.top+4, bottom: element_box.top+5},
-            Consumers => TermBox{left: element_box.left, right: element_box.right, top: element_box.top+5, bottom: element_box.top+6},
+            Currency  => TermBox{left: element_box.left, right: element_box.right, top: element_box.top+2, bottom: element_box.top+3},
+            Desc      => TermBox{left: element_box.left, right: element_box.right, top: element_box.top+3, bottom: element_box.top+4},
+            Tag       => TermBox{left: element_box.left, right: element_box.right, top: element_box.top+4, bottom: element_box.top+5},
+            Buyer     => TermBox{left: element_box.left, right: element_box.right, top: element_box.top+5, bottom: element_box.top+6},
+            Consumers => TermBox{left: element_box.left, right: element_box.right, top: element_box.top+6, bottom: element_box.top+7},
         }
     }
 }
@@ -659,12 +1041,22 @@ impl TermElement for PurchaseInput {
         let event_opt = match self.focus {
             Date      =>      self.date.input(event),
             Amount    =>    self.amount.input(event),
+            Currency  =>  self.currency.input(event),
             Desc      =>      self.desc.input(event),
             Tag       =>       self.tag.input(event),
             Buyer     =>     self.buyer.input(event),
             Consumers => self.consumers.input(event),
         };
 
+        if self.focus == Desc && matches!(event_opt, Some(InputEvent::Tab | InputEvent::Enter)) {
+            self.prefill();
+        }
+
+        if self.focus == Currency {
+            let currency = if self.currency.is_empty() { self.cfg.borrow().format().symbol.clone() } else { self.currency.get() };
+            self.amount.set_currency(currency);
+        }
+
         use InputEvent::*;
 
         match event_opt {
@@ -685,60 +1077,1412 @@ impl TermElement for PurchaseInput {
     }
 }
 
+/// Amounts in another currency are left unconverted, see `Rates::apply`.
 impl From<PurchaseInput> for Purchase {
     fn from(purchase: PurchaseInput) -> Purchase {
+        let amount: CentsAmount = purchase.amount.into();
+        let foreign = (!purchase.currency.is_empty()).then(|| ForeignAmount{currency: purchase.currency.into(), amount});
         Purchase {
             date: purchase.date.into(),
-            amount: purchase.amount.into(),
+            amount,
+            foreign,
             desc: purchase.desc.into(),
             tag: purchase.tag.into(),
             buyer: purchase.buyer.into(),
             consumers: purchase.consumers.into(),
+            recurring: purchase.recurring,
+            import_id: purchase.import_id,
         }
     }
 }
 
 
 
-#[derive(Clone)]
-pub struct InteractiveTransactions {
-    transactions: Transactions,
-    selection: usize,
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum TransferInputFocus {
+    Date,
+    Amount,
+    Desc,
+    From,
+    To,
+}
+
+impl TransferInputFocus {
+    pub fn new() -> Self {
+        Self::Date
+    }
+
+    pub fn next(&mut self) {
+        use TransferInputFocus::*;
+        *self = match self {
+            Date => Amount,
+            Amount => Desc,
+            Desc => From,
+            From => To,
+            To => Date,
+        }
+    }
+
+    pub fn prev(&mut self) {
+        use TransferInputFocus::*;
+        *self = match self {
+            Date => To,
+            Amount => Date,
+            Desc => Amount,
+            From => Desc,
+            To => From,
+        }
+    }
+
+    pub fn last(&self) -> bool {
+        use TransferInputFocus::*;
+        self == &To
+    }
+
+    pub fn all() -> [Self; 5] {
+        use TransferInputFocus::*;
+        [Date, Amount, Desc, From, To]
+    }
+}
+
+#[derive(Clone)]
+pub struct TransferInput {
+    focus: TransferInputFocus,
+    date: DateInput,
+    amount: AmountInput,
+    desc: CompletorInput,
+    from: CompletorInput,
+    to: CompletorInput,
+}
+
+impl TransferInput {
+    pub fn new(date: Date, desc_completor: Completor, account_completor: Completor, cfg: Rc<RefCell<LocalCfg>>) -> Self {
+        Self{
+            focus: TransferInputFocus::new(),
+            date: DateInput::new(date, Rc::clone(&cfg)),
+            amount: AmountInput::new(cfg),
+            desc: CompletorInput::new('"', '"', false, desc_completor),
+            from: CompletorInput::new('[', ']', true, account_completor.clone()),
+            to: CompletorInput::new('[', ']', true, account_completor),
+        }
+    }
+
+    pub fn from_transfer(transfer: &Transfer, desc_completor: Completor, account_completor: Completor, cfg: Rc<RefCell<LocalCfg>>) -> Self {
+        let mut ret = Self::new(transfer.date.clone(), desc_completor, account_completor, cfg);
+        ret.amount.set(transfer.amount);
+        ret.desc.set(transfer.desc.clone());
+        ret.from.set(transfer.from.clone());
+        ret.to.set(transfer.to.clone());
+        ret
+    }
+
+    pub fn valid(&self) -> bool {
+        self.amount.valid() && self.desc.valid() && self.from.valid() && self.to.valid() && self.from.get() != self.to.get()
+    }
+
+    fn child(&self, index: TransferInputFocus) -> &dyn TermElement {
+        use TransferInputFocus::*;
+
+        match index {
+            Date   =>   &self.date,
+            Amount => &self.amount,
+            Desc   =>   &self.desc,
+            From   =>   &self.from,
+            To     =>     &self.to,
+        }
+    }
+
+    #[allow(clippy::identity_op)]
+    fn child_box(&self, index: TransferInputFocus, element_box: TermBox) -> TermBox {
+        use TransferInputFocus::*;
+
+        match index {
+            Date   => TermBox{left: element_box.left, right: element_box.right, top: element_box.top+0, bottom: element_box.top+1},
+            Amount => TermBox{left: element_box.left, right: element_box.right, top: element_box.top+1, bottom: element_box.top+2},
+            Desc   => TermBox{left: element_box.left, right: element_box.right, top: element_box.top+2, bottom: element_box.top+3},
+            From   => TermBox{left: element_box.left, right: element_box.right, top: element_box.top+3, bottom: element_box.top+4},
+            To     => TermBox{left: element_box.left, right: element_box.right, top: element_box.top+4, bottom: element_box.top+5},
+        }
+    }
+}
+
+impl TermElement for TransferInput {
+    fn display(&self, element_box: TermBox, _active: bool) -> crossterm::Result<()> {
+        for index in TransferInputFocus::all() {
+            self.child(index).display(self.child_box(index, element_box), index == self.focus)?;
+        }
+
+        Ok(())
+    }
+
+    fn popup(&self, element_box: TermBox, window_box: TermBox) -> crossterm::Result<()> {
+        self.child(self.focus).popup(self.child_box(self.focus, element_box), window_box)
+    }
+
+    fn set_cursor(&self, element_box: TermBox, window_box: TermBox) -> crossterm::Result<()> {
+        self.child(self.focus).set_cursor(self.child_box(self.focus, element_box), window_box)
+    }
+
+    fn input(&mut self, event: InputEvent) -> Option<InputEvent> {
+        use TransferInputFocus::*;
+
+        let event_opt = match self.focus {
+            Date   =>   self.date.input(event),
+            Amount => self.amount.input(event),
+            Desc   =>   self.desc.input(event),
+            From   =>   self.from.input(event),
+            To     =>     self.to.input(event),
+        };
+
+        use InputEvent::*;
+
+        match event_opt {
+            Some(Tab | Enter) => {
+                if self.focus.last() && self.valid() {
+                    event_opt
+                } else {
+                    self.focus.next();
+                    None
+                }
+            },
+            Some(BackTab) => {
+                self.focus.prev();
+                None
+            },
+            _ => event_opt,
+        }
+    }
+}
+
+impl From<TransferInput> for Transfer {
+    fn from(transfer: TransferInput) -> Transfer {
+        Transfer {
+            date: transfer.date.into(),
+            amount: transfer.amount.into(),
+            desc: transfer.desc.into(),
+            from: transfer.from.into(),
+            to: transfer.to.into(),
+        }
+    }
+}
+
+
+
+#[derive(Clone)]
+pub enum TransactionInput {
+    Purchase(Box<PurchaseInput>),
+    Transfer(Box<TransferInput>),
+}
+
+impl TransactionInput {
+    pub fn date(&self) -> Date {
+        match self {
+            TransactionInput::Purchase(purchase) => purchase.date.date.clone(),
+            TransactionInput::Transfer(transfer) => transfer.date.date.clone(),
+        }
+    }
+
+    fn inner(&self) -> &dyn TermElement {
+        match self {
+            TransactionInput::Purchase(purchase) => purchase.as_ref(),
+            TransactionInput::Transfer(transfer) => transfer.as_ref(),
+        }
+    }
+}
+
+impl TermElement for TransactionInput {
+    fn display(&self, element_box: TermBox, active: bool) -> crossterm::Result<()> {
+        self.inner().display(element_box, active)
+    }
+
+    fn popup(&self, element_box: TermBox, window_box: TermBox) -> crossterm::Result<()> {
+        self.inner().popup(element_box, window_box)
+    }
+
+    fn set_cursor(&self, element_box: TermBox, window_box: TermBox) -> crossterm::Result<()> {
+        self.inner().set_cursor(element_box, window_box)
+    }
+
+    fn input(&mut self, event: InputEvent) -> Option<InputEvent> {
+        match self {
+            TransactionInput::Purchase(purchase) => purchase.input(event),
+            TransactionInput::Transfer(transfer) => transfer.input(event),
+        }
+    }
+}
+
+impl From<TransactionInput> for Transaction {
+    fn from(input: TransactionInput) -> Transaction {
+        match input {
+            TransactionInput::Purchase(purchase) => Transaction::Purchase((*purchase).into()),
+            TransactionInput::Transfer(transfer) => Transaction::Transfer((*transfer).into()),
+        }
+    }
+}
+
+
+#[derive(Debug, Clone)]
+enum Edit {
+    Add(Transaction),
+    Remove(Transaction),
+    Replace(Transaction, Transaction),
+}
+
+impl Edit {
+    fn describe(&self) -> String {
+        match self {
+            Edit::Add(tr) => format!("add \"{}\"", tr.desc()),
+            Edit::Remove(tr) => format!("removal of \"{}\"", tr.desc()),
+            Edit::Replace(old, _) => format!("edit of \"{}\"", old.desc()),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct InteractiveTransactions {
+    transactions: Transactions,
+    selection: usize,
+    undo_stack: Vec<Edit>,
+    redo_stack: Vec<Edit>,
+    path: PathBuf,
+    save_error: Option<String>,
 }
 
 impl InteractiveTransactions {
-    pub fn new(transactions: Transactions) -> Self {
-        Self{transactions, selection: 0}
+    pub fn new(transactions: Transactions, path: PathBuf) -> Self {
+        Self{transactions, selection: 0, undo_stack: Vec::new(), redo_stack: Vec::new(), path, save_error: None}
     }
 
     pub fn transactions(&self) -> &Transactions {
         &self.transactions
     }
 
+    pub fn save(&mut self) {
+        if let Err(err) = self.transactions.write_yaml_with_backup(&self.path, BACKUP_DAYS) {
+            self.save_error = Some(err.to_string());
+        }
+    }
+
+    pub fn take_save_error(&mut self) -> Option<String> {
+        self.save_error.take()
+    }
+
+    fn record(&mut self, edit: Edit) {
+        self.undo_stack.push(edit);
+        self.redo_stack.clear();
+        self.save();
+    }
+
+    fn position(&self, transaction: &Transaction) -> Option<usize> {
+        self.transactions.vec().iter().position(|tr| tr == transaction)
+    }
+
     pub fn add(&mut self, transaction: Transaction) -> usize {
-        let index = self.transactions.add(transaction);
+        let index = self.transactions.add(transaction.clone());
         self.selection = index;
+        self.record(Edit::Add(transaction));
         index
     }
 
+    pub fn selected(&self) -> Option<&Transaction> {
+        self.transactions.vec().get(self.selection)
+    }
+
+    /// Replaces the selected transaction, if there is one.
+    pub fn replace(&mut self, transaction: Transaction) -> Option<usize> {
+        let old = self.selected().cloned()?;
+        let index = self.transactions.replace(self.selection, transaction.clone());
+        self.selection = index;
+        self.record(Edit::Replace(old, transaction));
+        Some(index)
+    }
+
     pub fn remove(&mut self) {
         if self.selection < self.transactions.len() {
-            self.transactions.remove(self.selection);
-            if self.selection == self.transactions.len() {
-                self.prev();
+            let old = self.transactions.vec()[self.selection].clone();
+            self.remove_at(self.selection);
+            self.record(Edit::Remove(old));
+        }
+    }
+
+    fn remove_at(&mut self, index: usize) {
+        self.transactions.remove(index);
+        self.selection = index;
+        if self.selection >= self.transactions.len() {
+            self.selection = self.transactions.len().saturating_sub(1);
+        }
+    }
+
+    /// Applies `edit`, or reverts it, unless the transaction it changed is no longer there.
+    fn apply(&mut self, edit: &Edit, reverse: bool) -> Option<()> {
+        match (edit, reverse) {
+            (Edit::Add(tr), false) | (Edit::Remove(tr), true) => {
+                self.selection = self.transactions.add(tr.clone());
+            },
+            (Edit::Add(tr), true) | (Edit::Remove(tr), false) => {
+                let index = self.position(tr)?;
+                self.remove_at(index);
+            },
+            (Edit::Replace(old, new), false) => {
+                let index = self.position(old)?;
+                self.selection = self.transactions.replace(index, new.clone());
+            },
+            (Edit::Replace(old, new), true) => {
+                let index = self.position(new)?;
+                self.selection = self.transactions.replace(index, old.clone());
+            },
+        }
+        Some(())
+    }
+
+    /// Edits that can no longer be applied are dropped from the history.
+    pub fn undo(&mut self) -> String {
+        match self.undo_stack.pop() {
+            Some(edit) => {
+                if self.apply(&edit, true).is_none() {
+                    return format!("Cannot undo {}, the transaction was not found", edit.describe());
+                }
+                self.save();
+                let ret = format!("Undone: {}", edit.describe());
+                self.redo_stack.push(edit);
+                ret
+            },
+            None => "Nothing to undo".to_string(),
+        }
+    }
+
+    pub fn redo(&mut self) -> String {
+        match self.redo_stack.pop() {
+            Some(edit) => {
+                if self.apply(&edit, false).is_none() {
+                    return format!("Cannot redo {}, the transaction was not found", edit.describe());
+                }
+                self.save();
+                let ret = format!("Redone: {}", edit.describe());
+                self.undo_stack.push(edit);
+                ret
+            },
+            None => "Nothing to redo".to_string(),
+        }
+    }
+
+    pub fn prev(&mut self) {
+        if self.selection > 0 {
+            self.selection -= 1;
+        }
+    }
+
+    pub fn next(&mut self) {
+        if self.selection + 1 < self.transactions.len() {
+            self.selection += 1;
+        }
+    }
+}
+
+
+
+pub fn stylize_amount(amount: SignedCentsAmount, unit: Unit, width: usize, active: bool, format: &Formatting) -> crossterm::style::StyledContent<String> {
+    use crossterm::style::Color;
+    use std::cmp::Ordering::*;
+    let color = match amount.cents().cmp(&0) {
+        Less => Color::Red,
+        Equal => Color::Reset,
+        Greater => Color::Green,
+    };
+    let bold = amount.cents() != 0;
+    let symbol = format.symbol(unit);
+    assert!(width>symbol.chars().count());
+    let text = format.place_symbol(&amount.as_string_width(width-symbol.chars().count(), format.marks(true)), symbol);
+    simple_stylize(format!("{: >width$}", text, width = width), color, bold, active)
+}
+
+/// `budget` colored by how much of it is used, blank for tags without one.
+pub fn stylize_budget(budget: Option<CentsAmount>, level: Level, width: usize, active: bool, format: &Formatting) -> crossterm::style::StyledContent<String> {
+    use crossterm::style::Color;
+    let Some(budget) = budget else {
+        return simple_stylize(" ".repeat(width), Color::Reset, false, active);
+    };
+    let symbol = format.symbol(Unit::Currency);
+    assert!(width>symbol.chars().count());
+    let text = format.place_symbol(&budget.as_string_width(width-symbol.chars().count(), format.marks(true)), symbol);
+    simple_stylize(format!("{: >width$}", text, width = width), level.color(), true, active)
+}
+
+impl Level {
+    fn color(self) -> crossterm::style::Color {
+        use crossterm::style::Color;
+        match self {
+            Level::Under => Color::Reset,
+            Level::Projected => Color::Yellow,
+            Level::Over => Color::Red,
+        }
+    }
+}
+
+pub fn stylize_account(account: AccountRef, short: bool, active: bool, accounts_data: &Accounts) -> crossterm::style::StyledContent<String> {
+    use crossterm::style::Color;
+    let color: Color = accounts_data.0.get(&account).unwrap().color().into();
+    if short {
+        simple_stylize(account[0..1].to_string(), color, true, active)
+    } else {
+        simple_stylize(account, color, true, active)
+    }
+}
+
+
+
+/// Restriction of the transactions shown in the list, every criterion being optional.
+#[derive(Clone, Default)]
+pub struct ListFilter {
+    pub search: String,
+    pub tag: Option<TagRef>,
+    pub account: Option<AccountRef>,
+    pub from: Option<Date>,
+    pub to: Option<Date>,
+    pub min: Option<CentsAmount>,
+    pub max: Option<CentsAmount>,
+}
+
+impl ListFilter {
+    pub fn is_empty(&self) -> bool {
+        self.search.is_empty() && self.tag.is_none() && self.account.is_none() && self.from.is_none() && self.to.is_none() && self.min.is_none() && self.max.is_none()
+    }
+
+    pub fn matches(&self, transaction: &Transaction, tags: &Tags) -> bool {
+        let tag_ok = match (&self.tag, transaction) {
+            (None, _) => true,
+            (Some(tag), Transaction::Purchase(purchase)) => tags.is_descendant(&purchase.tag, tag),
+            (Some(_), Transaction::Transfer(_)) => false,
+        };
+        let amount = transaction.abs_amount();
+        tag_ok
+            && transaction.desc().to_lowercase().contains(&self.search.to_lowercase())
+            && self.account.as_ref().is_none_or(|account| transaction.accounts().contains(account))
+            && self.from.as_ref().is_none_or(|from| transaction.date() >= from)
+            && self.to.as_ref().is_none_or(|to| transaction.date() <= to)
+            && self.min.is_none_or(|min| amount >= min)
+            && self.max.is_none_or(|max| amount <= max)
+    }
+
+    /// Short description of the active criteria.
+    pub fn describe(&self, format: &Formatting) -> String {
+        let mut parts = Vec::new();
+        if !self.search.is_empty() {
+            parts.push(format!("\"{}\"", self.search));
+        }
+        if let Some(tag) = &self.tag {
+            parts.push(format!("<{tag}>"));
+        }
+        if let Some(account) = &self.account {
+            parts.push(format!("[{account}]"));
+        }
+        match (&self.from, &self.to) {
+            (Some(from), Some(to)) => parts.push(format!("{}..{}", format.date(from), format.date(to))),
+            (Some(from), None) => parts.push(format!("from {}", format.date(from))),
+            (None, Some(to)) => parts.push(format!("until {}", format.date(to))),
+            (None, None) => (),
+        }
+        let amount = |amount: CentsAmount| format.with_symbol(&amount.as_string_exact(format.marks(true)), Unit::Currency);
+        match (self.min, self.max) {
+            (Some(min), Some(max)) => parts.push(format!("{}..{}", amount(min), amount(max))),
+            (Some(min), None) => parts.push(format!(">= {}", amount(min))),
+            (None, Some(max)) => parts.push(format!("<= {}", amount(max))),
+            (None, None) => (),
+        }
+        parts.join(" ")
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum FilterBarFocus {
+    Tag,
+    Account,
+    From,
+    To,
+    Min,
+    Max,
+}
+
+impl FilterBarFocus {
+    const ALL: [Self; 6] = [Self::Tag, Self::Account, Self::From, Self::To, Self::Min, Self::Max];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Tag => "Tag",
+            Self::Account => "Account",
+            Self::From => "From",
+            Self::To => "To",
+            Self::Min => "Min",
+            Self::Max => "Max",
+        }
+    }
+
+    fn index(self) -> usize {
+        Self::ALL.iter().position(|x| *x == self).unwrap()
+    }
+
+    fn next(&mut self) {
+        *self = Self::ALL[(self.index() + 1) % Self::ALL.len()];
+    }
+
+    fn prev(&mut self) {
+        *self = Self::ALL[(self.index() + Self::ALL.len() - 1) % Self::ALL.len()];
+    }
+}
+
+/// One line of inputs for the criteria of a `ListFilter` other than the search.
+#[derive(Clone)]
+pub struct FilterBar {
+    tag: CompletorInput,
+    account: CompletorInput,
+    from: CompletorInput,
+    to: CompletorInput,
+    min: CompletorInput,
+    max: CompletorInput,
+    focus: FilterBarFocus,
+}
+
+impl FilterBar {
+    pub fn new(tag_completor: Completor, account_completor: Completor) -> Self {
+        let free = || CompletorInput::new('(', ')', false, Completor::new(Vec::new()));
+        Self {
+            tag: CompletorInput::new('<', '>', true, tag_completor),
+            account: CompletorInput::new('[', ']', true, account_completor),
+            from: free(),
+            to: free(),
+            min: free(),
+            max: free(),
+            focus: FilterBarFocus::Tag,
+        }
+    }
+
+    fn field(&self, focus: FilterBarFocus) -> &CompletorInput {
+        match focus {
+            FilterBarFocus::Tag => &self.tag,
+            FilterBarFocus::Account => &self.account,
+            FilterBarFocus::From => &self.from,
+            FilterBarFocus::To => &self.to,
+            FilterBarFocus::Min => &self.min,
+            FilterBarFocus::Max => &self.max,
+        }
+    }
+
+    fn field_mut(&mut self, focus: FilterBarFocus) -> &mut CompletorInput {
+        match focus {
+            FilterBarFocus::Tag => &mut self.tag,
+            FilterBarFocus::Account => &mut self.account,
+            FilterBarFocus::From => &mut self.from,
+            FilterBarFocus::To => &mut self.to,
+            FilterBarFocus::Min => &mut self.min,
+            FilterBarFocus::Max => &mut self.max,
+        }
+    }
+
+    fn field_box(&self, focus: FilterBarFocus, element_box: TermBox) -> TermBox {
+        let mut left = element_box.left;
+        for other in FilterBarFocus::ALL {
+            left += other.label().len() + 1;
+            if other == focus {
+                break;
             }
+            left += self.field(other).display_len() + 2;
+        }
+        TermBox{left, right: element_box.right, top: element_box.top, bottom: element_box.top+1}
+    }
+
+    pub fn clear(&mut self) {
+        for focus in FilterBarFocus::ALL {
+            self.field_mut(focus).clear();
         }
+        self.focus = FilterBarFocus::Tag;
+    }
+
+    /// Criteria of the bar, ignoring the fields that do not parse.
+    pub fn filter(&self, format: &Formatting) -> ListFilter {
+        let parse_date = |input: &CompletorInput| format.parse_date(&input.get());
+        let parse_amount = |input: &CompletorInput| CentsAmount::parse(&input.get());
+        ListFilter {
+            search: String::new(),
+            tag: Some(self.tag.get()).filter(|_| self.tag.valid()),
+            account: Some(self.account.get()).filter(|_| self.account.valid()),
+            from: parse_date(&self.from),
+            to: parse_date(&self.to),
+            min: parse_amount(&self.min),
+            max: parse_amount(&self.max),
+        }
+    }
+}
+
+impl TermElement for FilterBar {
+    fn display(&self, element_box: TermBox, active: bool) -> crossterm::Result<()> {
+        use crossterm::{queue, style::Print};
+
+        for focus in FilterBarFocus::ALL {
+            let field_box = self.field_box(focus, element_box);
+            TermPos::new(field_box.left - focus.label().len() - 1, field_box.top).goto()?;
+            queue!(stdout(), Print(focus.label()))?;
+            self.field(focus).display(field_box, active && focus == self.focus)?;
+        }
+        Ok(())
+    }
+
+    fn popup(&self, element_box: TermBox, window_box: TermBox) -> crossterm::Result<()> {
+        self.field(self.focus).popup(self.field_box(self.focus, element_box), window_box)
+    }
+
+    fn set_cursor(&self, element_box: TermBox, window_box: TermBox) -> crossterm::Result<()> {
+        self.field(self.focus).set_cursor(self.field_box(self.focus, element_box), window_box)
+    }
+
+    fn input(&mut self, event: InputEvent) -> Option<InputEvent> {
+        use InputEvent::*;
+
+        match self.field_mut(self.focus).input(event) {
+            Some(Tab) => {
+                self.focus.next();
+                None
+            },
+            Some(BackTab) => {
+                self.focus.prev();
+                None
+            },
+            event_opt => event_opt,
+        }
+    }
+}
+
+
+
+#[derive(Clone)]
+pub struct TransactionsTE {
+    transactions: Rc<RefCell<InteractiveTransactions>>,
+    accounts: Rc<RefCell<Accounts>>,
+    tags: Rc<RefCell<Tags>>,
+    cfg: Rc<RefCell<LocalCfg>>,
+    search: String,
+    searching: bool,
+    bar: FilterBar,
+    bar_active: bool,
+}
+
+impl TransactionsTE {
+    pub fn new(transactions: Rc<RefCell<InteractiveTransactions>>, accounts: Rc<RefCell<Accounts>>, tags: Rc<RefCell<Tags>>, cfg: Rc<RefCell<LocalCfg>>) -> Self {
+        let tag_completor = Completor::new(tags.borrow().clone().0.into_keys().collect());
+        let account_completor = Completor::new(accounts.borrow().clone().0.into_keys().collect());
+        let bar = FilterBar::new(tag_completor, account_completor);
+        Self{transactions, accounts, tags, cfg, search: String::new(), searching: false, bar, bar_active: false}
+    }
+
+    pub fn filter(&self) -> ListFilter {
+        ListFilter{search: self.search.clone(), ..self.bar.filter(self.cfg.borrow().format())}
+    }
+
+    fn clear_filter(&mut self) {
+        self.search.clear();
+        self.searching = false;
+        self.bar.clear();
+        self.bar_active = false;
+    }
+
+    /// Whether a line above the list shows the search, the filter bar or the active filter.
+    fn has_filter_line(&self) -> bool {
+        self.searching || self.bar_active || !self.filter().is_empty()
+    }
+
+    /// Indices of the transactions shown, the matching ones and the selected one.
+    fn visible(&self) -> Vec<usize> {
+        let filter = self.filter();
+        let tags = self.tags.borrow();
+        let transactions = self.transactions.borrow();
+        transactions.transactions().vec().iter().enumerate()
+            .filter(|(index, transaction)| *index == transactions.selection || filter.matches(transaction, &tags))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Moves the selection to the nearest matching transaction if it does not match anymore.
+    fn fix_selection(&self) {
+        let filter = self.filter();
+        let tags = self.tags.borrow();
+        let mut transactions = self.transactions.borrow_mut();
+        let list = transactions.transactions().vec();
+        let selection = transactions.selection;
+        let matches = |index: &usize| filter.matches(&list[*index], &tags);
+        if selection >= list.len() || matches(&selection) {
+            return;
+        }
+        if let Some(index) = (selection..list.len()).find(matches).or_else(|| (0..selection).rev().find(matches)) {
+            transactions.selection = index;
+        }
+    }
+
+    fn move_selection(&self, forward: bool) {
+        let visible = self.visible();
+        let mut transactions = self.transactions.borrow_mut();
+        let Some(position) = visible.iter().position(|index| *index == transactions.selection) else {
+            return;
+        };
+        let position = if forward { usize::min(position + 1, visible.len() - 1) } else { position.saturating_sub(1) };
+        transactions.selection = visible[position];
+    }
+
+    fn display_filter_line(&self, element_box: TermBox) -> crossterm::Result<()> {
+        use crossterm::{
+            queue,
+            style::{Print, PrintStyledContent, Stylize},
+        };
+
+        if self.bar_active {
+            return self.bar.display(element_box, true);
+        }
+
+        element_box.begin().goto()?;
+        if self.searching {
+            queue!(stdout(), PrintStyledContent(format!("/{}", self.search).bold()))?;
+            return Ok(());
+        }
+
+        let filter = self.filter();
+        let tags = self.tags.borrow();
+        let transactions = self.transactions.borrow();
+        let cfg = self.cfg.borrow();
+        let account = &cfg.account;
+        let (mut count, mut internal, mut external) = (0, 0, 0);
+        for transaction in transactions.transactions().vec().iter().filter(|transaction| filter.matches(transaction, &tags)) {
+            count += 1;
+            internal += transaction.internal_delta(account).cents();
+            external += transaction.external_delta(account).cents();
+        }
+        let amount = |cents| cfg.format.with_symbol(&SignedCentsAmount::new(cents).as_string_exact(cfg.format.marks(true)), Unit::Currency);
+        let text = format!("Filter: {} | {count} transactions, internal {}, external {}", filter.describe(&cfg.format), amount(internal), amount(external));
+        queue!(stdout(), Print(truncate_align_left(&text, element_box.width())))?;
+        Ok(())
+    }
+
+    fn display_transaction(transaction: &Transaction, element_box: TermBox, active: bool, cfg_data: &LocalCfg, tags_data: &Tags, accounts_data: &Accounts, transactions_data: &Transactions) -> crossterm::Result<()> {
+        use crossterm::{
+            queue,
+            style::{Print, PrintStyledContent, Color},
+        };
+
+        assert_eq!(element_box.height(), 1);
+
+        let format = &cfg_data.format;
+        let date_cf = (format.date_width(), 0);
+        let amount_cf = (8, 1);
+        let space_cf = (1, 0);
+        let desc_cf = (6, 20);
+        let kind_cf = (6, 4);
+        let accounts_cf = (6, 4);
+
+        let [date_width, _, kind_width, _, desc_width, _, accounts_width, _, internal_delta_width, _, external_delta_width, _, internal_flow_width] = subdiv_const_flex(element_box.width(), [date_cf, space_cf, kind_cf, space_cf, desc_cf, space_cf, accounts_cf, space_cf, amount_cf, space_cf, amount_cf, space_cf, amount_cf]);
+
+        assert_eq!(date_width, format.date_width());
+
+        element_box.begin().goto()?;
+
+        let space = simple_stylize(" ", Color::Reset, true, active);
+
+        let date = simple_stylize(truncate_align_left(&format.date(transaction.date()), date_width), Color::Reset, true, active);
+
+        fn stylize_accounts(accounts: Vec<AccountRef>, separator: &str, width: usize, active: bool, accounts_data: &Accounts) -> String {
+            if accounts.is_empty() {
+                return simple_stylize(" ".repeat(width), Color::Reset, true, active).to_string();
+            }
+            let width_spaces = accounts.len() - 1;
+            let width_large = accounts.iter().map(|x| x.len()).sum::<usize>() + width_spaces;
+            let width_short = accounts.len() + width_spaces;
+            if width >= width_short {
+                let short = width < width_large;
+                let rem_width = width - (if short {width_short} else {width_large});
+                let mut ret = String::new();
+                let mut iter = accounts.into_iter();
+                ret += &simple_stylize(" ".repeat(rem_width/2), Color::Reset, true, active).to_string();
+                ret += &stylize_account(iter.next().unwrap(), short, active, accounts_data).to_string();
+                for account in iter {
+                    ret += &simple_stylize(separator, Color::Reset, true, active).to_string();
+                    ret += &stylize_account(account, short, active, accounts_data).to_string();
+                }
+                ret += &simple_stylize(" ".repeat(rem_width.div_ceil(2)), Color::Reset, true, active).to_string();
+                ret
+            } else {
+                simple_stylize(format!("{: ^width$}", "...", width = width), Color::Reset, true, active).to_string()
+            }
+        }
+
+        let kind = simple_stylize(truncate_align_left(&transaction.kind_str(), kind_width), Color::Reset, true, active);
+        let mut desc_text = match transaction.recurring() {
+            Some(_) => format!("↻ {}", transaction.desc()),
+            None => transaction.desc().clone(),
+        };
+        if let Some(foreign) = transaction.foreign() {
+            desc_text += &format!(" ({})", foreign.as_string(format.marks(false)));
+        }
+        let desc = simple_stylize(truncate_align_left(&desc_text, desc_width), Color::Reset, true, active);
+        let separator = match transaction {
+            Transaction::Purchase(_) => " ",
+            Transaction::Transfer(_) => ">",
+        };
+        let accounts = stylize_accounts(transaction.accounts(), separator, accounts_width, active, accounts_data);
+
+        let int_amount = stylize_amount(transaction.internal_delta(&cfg_data.account), Unit::Currency, internal_delta_width, active, format);
+        let ext_amount = stylize_amount(transaction.external_delta(&cfg_data.account), Unit::Currency, external_delta_width, active, format);
+
+        let flow = stylize_amount(transaction.internal_flow(&cfg_data.account, tags_data, transactions_data).0, Unit::Flow, internal_flow_width, active, format);
+
+        queue!(stdout(), PrintStyledContent(date), PrintStyledContent(space), PrintStyledContent(kind), PrintStyledContent(space), PrintStyledContent(desc), PrintStyledContent(space), Print(accounts), PrintStyledContent(space), PrintStyledContent(int_amount), PrintStyledContent(space), PrintStyledContent(ext_amount), PrintStyledContent(space), PrintStyledContent(flow))?;
+
+        Ok(())
+    }
+
+    fn display_transaction_header(element_box: TermBox, format: &Formatting) -> crossterm::Result<()> {
+        use crossterm::{
+            queue,
+            style::{PrintStyledContent, Color},
+        };
+
+        assert_eq!(element_box.height(), 1);
+
+        let date_cf = (format.date_width(), 0);
+        let amount_cf = (8, 1);
+        let space_cf = (1, 0);
+        let desc_cf = (6, 20);
+        let kind_cf = (6, 4);
+        let accounts_cf = (6, 4);
+
+        let [date_width, _, kind_width, _, desc_width, _, accounts_width, _, internal_delta_width, _, external_delta_width, _, internal_flow_width] = subdiv_const_flex(element_box.width(), [date_cf, space_cf, kind_cf, space_cf, desc_cf, space_cf, accounts_cf, space_cf, amount_cf, space_cf, amount_cf, space_cf, amount_cf]);
+
+        element_box.begin().goto()?;
+
+        let space = simple_stylize(" ", Color::Reset, true, false);
+
+        let date = simple_stylize(truncate_align_center("Date", date_width), Color::Reset, true, false);
+        let kind = simple_stylize(truncate_align_center("Kind", kind_width), Color::Reset, true, false);
+        let desc = simple_stylize(truncate_align_center("Description", desc_width), Color::Reset, true, false);
+        let accounts = simple_stylize(truncate_align_center("Accounts", accounts_width), Color::Reset, true, false);
+        let int_amount = simple_stylize(truncate_align_center("Internal", internal_delta_width), Color::Reset, true, false);
+        let ext_amount = simple_stylize(truncate_align_center("External", external_delta_width), Color::Reset, true, false);
+        let flow = simple_stylize(truncate_align_center("Flow", internal_flow_width), Color::Reset, true, false);
+
+        queue!(stdout(), PrintStyledContent(date), PrintStyledContent(space), PrintStyledContent(kind), PrintStyledContent(space), PrintStyledContent(desc), PrintStyledContent(space), PrintStyledContent(accounts), PrintStyledContent(space), PrintStyledContent(int_amount), PrintStyledContent(space), PrintStyledContent(ext_amount), PrintStyledContent(space), PrintStyledContent(flow))?;
+
+        Ok(())
+    }
+}
+
+impl TermElement for TransactionsTE {
+    fn display(&self, element_box: TermBox, _active: bool) -> crossterm::Result<()> {
+        use crossterm::{queue, style::Print};
+        let height = element_box.height();
+        if height < 5 {
+            element_box.begin().goto()?;
+            queue!(stdout(), Print("..."))?;
+            return Ok(());
+        }
+        let filter_height = usize::from(self.has_filter_line());
+        let list_height = height - 1 - filter_height;
+
+        let visible = self.visible();
+        let center_index = visible.iter().position(|index| *index == self.transactions.borrow().selection).unwrap_or(0);
+        let mut begin_index = center_index;
+        let mut end_index = center_index;
+
+        while end_index - begin_index < list_height {
+            let avail_begin = begin_index > 0;
+            let avail_end = end_index < visible.len();
+
+            match (avail_begin, avail_end) {
+                (true, true) if center_index - begin_index < end_index - center_index => begin_index -= 1,
+                (true, true) => end_index += 1,
+                (true, false) => begin_index -= 1,
+                (false, true) => end_index += 1,
+                (false, false) => break,
+            }
+        }
+
+        let filter_box = TermBox{left: element_box.left, right: element_box.right, top: element_box.top, bottom: element_box.top+1};
+        if filter_height > 0 {
+            self.display_filter_line(filter_box)?;
+        }
+
+        let top = element_box.top + filter_height;
+        let header_box = TermBox{left: element_box.left, right: element_box.right, top, bottom: top+1};
+        Self::display_transaction_header(header_box, self.cfg.borrow().format())?;
+
+        let transactions = self.transactions.borrow();
+        for (row, index) in visible[begin_index..end_index].iter().enumerate() {
+            let trans_selected = *index == transactions.selection;
+            let trans_box = TermBox{left: element_box.left, right: element_box.right, top: top+row+1, bottom: top+row+2};
+            Self::display_transaction(&transactions.transactions().vec()[*index], trans_box, trans_selected, &self.cfg.borrow(), &self.tags.borrow(), &self.accounts.borrow(), &transactions.transactions)?;
+        }
+
+        Ok(())
+    }
+
+    fn popup(&self, element_box: TermBox, window_box: TermBox) -> crossterm::Result<()> {
+        if self.bar_active {
+            self.bar.popup(element_box, window_box)?;
+        }
+        Ok(())
+    }
+
+    fn set_cursor(&self, element_box: TermBox, window_box: TermBox) -> crossterm::Result<()> {
+        use crossterm::{queue, cursor};
+        if self.bar_active {
+            self.bar.set_cursor(element_box, window_box)
+        } else if self.searching {
+            TermPos::new(element_box.left + self.search.chars().count() + 1, element_box.top).goto()?;
+            queue!(stdout(), cursor::Show, cursor::SetCursorStyle::BlinkingBar)
+        } else {
+            queue!(stdout(), cursor::Hide)
+        }
+    }
+
+    fn input(&mut self, event: InputEvent) -> Option<InputEvent> {
+        use InputEvent::*;
+
+        if self.bar_active {
+            match self.bar.input(event) {
+                Some(Enter) => self.bar_active = false,
+                Some(Esc) => {
+                    self.bar.clear();
+                    self.bar_active = false;
+                },
+                _ => (),
+            }
+            self.fix_selection();
+            return None;
+        }
+
+        if self.searching {
+            match event {
+                Char(c) => self.search.push(c),
+                Backspace => {
+                    let _ = self.search.pop();
+                },
+                Up => self.move_selection(false),
+                Down => self.move_selection(true),
+                Enter => self.searching = false,
+                Esc => {
+                    self.search.clear();
+                    self.searching = false;
+                },
+                _ => (),
+            }
+            self.fix_selection();
+            return None;
+        }
+
+        match event {
+            Up => {
+                self.move_selection(false);
+                None
+            },
+            Down => {
+                self.move_selection(true);
+                None
+            },
+            Char('d') => {
+                self.transactions.borrow_mut().remove();
+                self.fix_selection();
+                None
+            },
+            Char('/') => {
+                self.searching = true;
+                None
+            },
+            Char('F') => {
+                self.bar_active = true;
+                None
+            },
+            Esc if !self.filter().is_empty() => {
+                self.clear_filter();
+                None
+            },
+            _ => Some(event),
+        }
+    }
+}
+
+
+
+#[derive(Clone)]
+pub struct BalancesTE {
+    transactions: Rc<RefCell<InteractiveTransactions>>,
+    accounts: Rc<RefCell<Accounts>>,
+    cfg: Rc<RefCell<LocalCfg>>,
+    selection: usize,
+}
+
+impl BalancesTE {
+    pub fn new(transactions: Rc<RefCell<InteractiveTransactions>>, accounts: Rc<RefCell<Accounts>>, cfg: Rc<RefCell<LocalCfg>>) -> Self {
+        Self{transactions, accounts, cfg, selection: 0}
+    }
+
+    pub fn settle_selected(&mut self) {
+        let repayments = self.transactions.borrow().transactions().balances().settlement();
+        if let Some(repayment) = repayments.get(self.selection) {
+            let transfer = Transfer {
+                date: Date::today(),
+                from: repayment.from.clone(),
+                to: repayment.to.clone(),
+                amount: repayment.amount,
+                desc: "Settlement".to_string(),
+            };
+            self.transactions.borrow_mut().add(Transaction::Transfer(transfer));
+            let count = self.transactions.borrow().transactions().balances().settlement().len();
+            self.selection = usize::min(self.selection, count.saturating_sub(1));
+        }
+    }
+}
+
+impl TermElement for BalancesTE {
+    fn display(&self, element_box: TermBox, _active: bool) -> crossterm::Result<()> {
+        use crossterm::{
+            queue,
+            style::{Print, PrintStyledContent, Color},
+        };
+
+        let accounts_data = self.accounts.borrow();
+        let format = self.cfg.borrow().format().clone();
+        let balances = self.transactions.borrow().transactions().balances();
+        let repayments = balances.settlement();
+
+        let name_width = accounts_data.0.keys().map(|x| x.len()).max().unwrap_or(0);
+        let amount_width = 12;
+
+        let mut row = element_box.top;
+
+        TermPos::new(element_box.left, row).goto()?;
+        queue!(stdout(), PrintStyledContent(simple_stylize("Balances", Color::Reset, true, false)))?;
+        row += 1;
+
+        for (account, balance) in &balances.0 {
+            if row >= element_box.bottom {
+                return Ok(());
+            }
+            TermPos::new(element_box.left, row).goto()?;
+            let name = stylize_account(account.clone(), false, false, &accounts_data);
+            let padding = " ".repeat(name_width.saturating_sub(account.len()) + 1);
+            let amount = stylize_amount(balance.external(), Unit::Currency, amount_width, false, &format);
+            queue!(stdout(), PrintStyledContent(name), Print(padding), PrintStyledContent(amount))?;
+            row += 1;
+        }
+
+        row += 1;
+        if row >= element_box.bottom {
+            return Ok(());
+        }
+        TermPos::new(element_box.left, row).goto()?;
+        if repayments.is_empty() {
+            queue!(stdout(), PrintStyledContent(simple_stylize("Everyone is settled", Color::Reset, true, false)))?;
+        } else {
+            queue!(stdout(), PrintStyledContent(simple_stylize("Repayments", Color::Reset, true, false)))?;
+        }
+        row += 1;
+
+        // The list of repayments scrolls to keep the selected one in the rows left.
+        let list_height = element_box.bottom.saturating_sub(row);
+        let begin_index = (self.selection + 1).saturating_sub(list_height);
+
+        for (n, repayment) in repayments.into_iter().enumerate().skip(begin_index).take(list_height) {
+            let active = n == self.selection;
+            TermPos::new(element_box.left, row).goto()?;
+            let from = stylize_account(repayment.from.clone(), false, active, &accounts_data);
+            let to = stylize_account(repayment.to.clone(), false, active, &accounts_data);
+            let arrow = simple_stylize(" -> ", Color::Reset, true, active);
+            let colon = simple_stylize(": ", Color::Reset, true, active);
+            let amount = simple_stylize(format.with_symbol(&repayment.amount.as_string_exact(format.marks(true)), Unit::Currency), Color::Reset, true, active);
+            queue!(stdout(), PrintStyledContent(from), PrintStyledContent(arrow), PrintStyledContent(to), PrintStyledContent(colon), PrintStyledContent(amount))?;
+            row += 1;
+        }
+
+        Ok(())
+    }
+
+    fn popup(&self, _element_box: TermBox, _window_box: TermBox) -> crossterm::Result<()> {
+        Ok(())
+    }
+
+    fn set_cursor(&self, _element_box: TermBox, _window_box: TermBox) -> crossterm::Result<()> {
+        use crossterm::{queue, cursor};
+        queue!(stdout(), cursor::Hide)
+    }
+
+    fn input(&mut self, event: InputEvent) -> Option<InputEvent> {
+        use InputEvent::*;
+
+        match event {
+            Up => {
+                if self.selection > 0 {
+                    self.selection -= 1;
+                }
+                None
+            },
+            Down => {
+                let count = self.transactions.borrow().transactions().balances().settlement().len();
+                if self.selection + 1 < count {
+                    self.selection += 1;
+                }
+                None
+            },
+            Enter => {
+                self.settle_selected();
+                None
+            },
+            _ => Some(event),
+        }
+    }
+}
+
+
+
+#[derive(Clone)]
+pub struct TagsTE {
+    transactions: Rc<RefCell<InteractiveTransactions>>,
+    tags: Rc<RefCell<Tags>>,
+    cfg: Rc<RefCell<LocalCfg>>,
+    collapsed: BTreeSet<TagRef>,
+    selection: usize,
+}
+
+impl TagsTE {
+    pub fn new(transactions: Rc<RefCell<InteractiveTransactions>>, tags: Rc<RefCell<Tags>>, cfg: Rc<RefCell<LocalCfg>>) -> Self {
+        Self{transactions, tags, cfg, collapsed: BTreeSet::new(), selection: 0}
+    }
+
+    fn rows(&self) -> Vec<TagRef> {
+        self.tags.borrow().tree(&self.collapsed)
+    }
+}
+
+impl TermElement for TagsTE {
+    fn display(&self, element_box: TermBox, _active: bool) -> crossterm::Result<()> {
+        use crossterm::{
+            queue,
+            style::{PrintStyledContent, Color},
+        };
+
+        let height = element_box.height();
+        let cfg = self.cfg.borrow();
+        let weights = [(6, 4), (1, 0), (8, 1), (1, 0), (8, 1), (1, 0), (8, 1), (1, 0), (8, 1)];
+        if height < 5 || element_box.width() <= weights.iter().map(|(c, _)| c).sum::<usize>() {
+            element_box.begin().goto()?;
+            queue!(stdout(), crossterm::style::Print("..."))?;
+            return Ok(());
+        }
+
+        let tags_data = self.tags.borrow();
+        let transactions = self.transactions.borrow();
+        let account = cfg.account().clone();
+
+        let today = Date::today();
+        let spent = tags_data.rollup(&transactions.transactions().spending(&account), SignedCentsAmount::new(0));
+        let month = tags_data.rollup(&transactions.transactions().spending_between(&today.first_of_month(), &today.last_of_month(), &account), SignedCentsAmount::new(0));
+        let flows = tags_data.rollup(&transactions.transactions().flows_at(&today, &account, &tags_data), CentsAmount::new(0));
+        let budgets: BTreeMap<TagRef, BudgetStatus> = budget::statuses(transactions.transactions(), &tags_data, &account, &today).into_iter().map(|status| (status.tag.clone(), status)).collect();
+
+        let [name_width, _, spent_width, _, flow_width, _, month_width, _, budget_width] = subdiv_const_flex(element_box.width(), weights);
+        let space = simple_stylize(" ", Color::Reset, true, false);
+
+        element_box.begin().goto()?;
+        let name = simple_stylize(truncate_align_center("Tag", name_width), Color::Reset, true, false);
+        let spent_header = simple_stylize(truncate_align_center("Spent", spent_width), Color::Reset, true, false);
+        let flow_header = simple_stylize(truncate_align_center("Flow", flow_width), Color::Reset, true, false);
+        let month_header = simple_stylize(truncate_align_center("Month", month_width), Color::Reset, true, false);
+        let budget_header = simple_stylize(truncate_align_center("Budget", budget_width), Color::Reset, true, false);
+        queue!(stdout(), PrintStyledContent(name), PrintStyledContent(space), PrintStyledContent(spent_header), PrintStyledContent(space), PrintStyledContent(flow_header), PrintStyledContent(space), PrintStyledContent(month_header), PrintStyledContent(space), PrintStyledContent(budget_header))?;
+
+        let rows = self.rows();
+        let list_height = height - 1;
+        let begin_index = (self.selection + 1).saturating_sub(list_height);
+
+        for (index, tag) in rows.iter().enumerate().skip(begin_index).take(list_height) {
+            let active = index == self.selection;
+            let marker = if tags_data.children(Some(tag)).is_empty() {
+                ' '
+            } else if self.collapsed.contains(tag) {
+                '+'
+            } else {
+                '-'
+            };
+            let text = format!("{}{} {}", "  ".repeat(tags_data.depth(tag)), marker, tag);
+
+            let status = budgets.get(tag);
+            let level = status.map_or(Level::Under, BudgetStatus::level);
+
+            TermPos::new(element_box.left, element_box.top + 1 + index - begin_index).goto()?;
+            let space = simple_stylize(" ", Color::Reset, true, active);
+            let name = simple_stylize(truncate_align_left(&text, name_width), level.color(), true, active);
+            let spent = stylize_amount(spent[tag], Unit::Currency, spent_width, active, cfg.format());
+            let flow = stylize_amount(SignedCentsAmount::negative(flows[tag]), Unit::Flow, flow_width, active, cfg.format());
+            let month = stylize_amount(month[tag], Unit::Currency, month_width, active, cfg.format());
+            let budget = stylize_budget(status.map(|status| status.budget), level, budget_width, active, cfg.format());
+            queue!(stdout(), PrintStyledContent(name), PrintStyledContent(space), PrintStyledContent(spent), PrintStyledContent(space), PrintStyledContent(flow), PrintStyledContent(space), PrintStyledContent(month), PrintStyledContent(space), PrintStyledContent(budget))?;
+        }
+
+        Ok(())
+    }
+
+    fn popup(&self, _element_box: TermBox, _window_box: TermBox) -> crossterm::Result<()> {
+        Ok(())
+    }
+
+    fn set_cursor(&self, _element_box: TermBox, _window_box: TermBox) -> crossterm::Result<()> {
+        use crossterm::{queue, cursor};
+        queue!(stdout(), cursor::Hide)
+    }
+
+    fn input(&mut self, event: InputEvent) -> Option<InputEvent> {
+        use InputEvent::*;
+
+        let rows = self.rows();
+        let selected = rows.get(self.selection).cloned();
+
+        match event {
+            Up => {
+                if self.selection > 0 {
+                    self.selection -= 1;
+                }
+                None
+            },
+            Down => {
+                if self.selection + 1 < rows.len() {
+                    self.selection += 1;
+                }
+                None
+            },
+            Right => {
+                if let Some(tag) = selected {
+                    self.collapsed.remove(&tag);
+                }
+                None
+            },
+            Left => {
+                if let Some(tag) = selected {
+                    if !self.collapsed.contains(&tag) && !self.tags.borrow().children(Some(&tag)).is_empty() {
+                        self.collapsed.insert(tag);
+                    } else if let Some(parent) = self.tags.borrow().parent(&tag) {
+                        self.selection = rows.iter().position(|x| x == parent).unwrap_or(self.selection);
+                    }
+                }
+                None
+            },
+            Enter => {
+                if let Some(tag) = selected {
+                    if !self.collapsed.remove(&tag) {
+                        self.collapsed.insert(tag);
+                    }
+                }
+                None
+            },
+            _ => Some(event),
+        }
+    }
+}
+
+
+
+#[derive(Clone)]
+pub struct BudgetsTE {
+    transactions: Rc<RefCell<InteractiveTransactions>>,
+    tags: Rc<RefCell<Tags>>,
+    cfg: Rc<RefCell<LocalCfg>>,
+    selection: usize,
+}
+
+impl BudgetsTE {
+    pub fn new(transactions: Rc<RefCell<InteractiveTransactions>>, tags: Rc<RefCell<Tags>>, cfg: Rc<RefCell<LocalCfg>>) -> Self {
+        Self{transactions, tags, cfg, selection: 0}
+    }
+
+    fn rows(&self) -> Vec<BudgetStatus> {
+        let account = self.cfg.borrow().account().clone();
+        budget::ranked(budget::statuses(self.transactions.borrow().transactions(), &self.tags.borrow(), &account, &Date::today()))
+    }
+}
+
+impl TermElement for BudgetsTE {
+    fn display(&self, element_box: TermBox, _active: bool) -> crossterm::Result<()> {
+        use crossterm::{
+            queue,
+            style::{Print, PrintStyledContent, Color},
+        };
+
+        let height = element_box.height();
+        let cfg = self.cfg.borrow();
+        let weights = [(6, 2), (1, 0), (8, 1), (1, 0), (8, 1), (1, 0), (8, 1), (1, 0), (6, 0), (1, 0), (4, 3)];
+        if height < 5 || element_box.width() <= weights.iter().map(|(c, _)| c).sum::<usize>() {
+            element_box.begin().goto()?;
+            queue!(stdout(), Print("..."))?;
+            return Ok(());
+        }
+
+        let rows = self.rows();
+        if rows.is_empty() {
+            element_box.begin().goto()?;
+            queue!(stdout(), Print("No budget set, add a budget to tags in tags.yaml"))?;
+            return Ok(());
+        }
+
+        let [name_width, _, month_width, _, projected_width, _, budget_width, _, use_width, _, bar_width] = subdiv_const_flex(element_box.width(), weights);
+        let space = simple_stylize(" ", Color::Reset, true, false);
+
+        element_box.begin().goto()?;
+        let headers = [("Tag", name_width), ("Month", month_width), ("Projected", projected_width), ("Budget", budget_width), ("Use", use_width), ("", bar_width)];
+        for (index, (header, width)) in headers.into_iter().enumerate() {
+            if index > 0 {
+                queue!(stdout(), PrintStyledContent(space))?;
+            }
+            queue!(stdout(), PrintStyledContent(simple_stylize(truncate_align_center(header, width), Color::Reset, true, false)))?;
+        }
+
+        let list_height = height - 1;
+        let begin_index = (self.selection + 1).saturating_sub(list_height);
+
+        for (index, status) in rows.iter().enumerate().skip(begin_index).take(list_height) {
+            let active = index == self.selection;
+            let level = status.level();
+
+            TermPos::new(element_box.left, element_box.top + 1 + index - begin_index).goto()?;
+            let space = simple_stylize(" ", Color::Reset, true, active);
+            let name = simple_stylize(truncate_align_left(&status.tag, name_width), level.color(), true, active);
+            let month = stylize_amount(SignedCentsAmount::negative(status.spent), Unit::Currency, month_width, active, cfg.format());
+            let projected = stylize_amount(SignedCentsAmount::negative(status.projected), Unit::Currency, projected_width, active, cfg.format());
+            let budget = stylize_budget(Some(status.budget), level, budget_width, active, cfg.format());
+            let percent = simple_stylize(format!("{: >width$}", status.percent_string(), width = use_width), level.color(), true, active);
+            let filled = usize::min((status.percent() as usize).saturating_mul(bar_width) / 100, bar_width);
+            let bar = simple_stylize(format!("{}{}", "█".repeat(filled), " ".repeat(bar_width - filled)), level.color(), false, false);
+            queue!(stdout(), PrintStyledContent(name), PrintStyledContent(space), PrintStyledContent(month), PrintStyledContent(space), PrintStyledContent(projected), PrintStyledContent(space), PrintStyledContent(budget), PrintStyledContent(space), PrintStyledContent(percent), PrintStyledContent(space), PrintStyledContent(bar))?;
+        }
+
+        Ok(())
+    }
+
+    fn popup(&self, _element_box: TermBox, _window_box: TermBox) -> crossterm::Result<()> {
+        Ok(())
+    }
+
+    fn set_cursor(&self, _element_box: TermBox, _window_box: TermBox) -> crossterm::Result<()> {
+        use crossterm::{queue, cursor};
+        queue!(stdout(), cursor::Hide)
     }
 
-    pub fn prev(&mut self) {
-        if self.selection > 0 {
-            self.selection -= 1;
-        }
-    }
+    fn input(&mut self, event: InputEvent) -> Option<InputEvent> {
+        use InputEvent::*;
 
-    pub fn next(&mut self) {
-        if self.selection + 1 < self.transactions.len() {
-            self.selection += 1;
+        match event {
+            Up => {
+                self.selection = self.selection.saturating_sub(1);
+                None
+            },
+            Down => {
+                if self.selection + 1 < self.rows().len() {
+                    self.selection += 1;
+                }
+                None
+            },
+            _ => Some(event),
         }
     }
 }
@@ -746,174 +2490,276 @@ impl InteractiveTransactions {
 
 
 #[derive(Clone)]
-pub struct TransactionsTE {
+pub struct ReportTE {
     transactions: Rc<RefCell<InteractiveTransactions>>,
     accounts: Rc<RefCell<Accounts>>,
     tags: Rc<RefCell<Tags>>,
     cfg: Rc<RefCell<LocalCfg>>,
+    period: Period,
+    date: Date,
+    selection: usize,
 }
 
-impl TransactionsTE {
+impl ReportTE {
     pub fn new(transactions: Rc<RefCell<InteractiveTransactions>>, accounts: Rc<RefCell<Accounts>>, tags: Rc<RefCell<Tags>>, cfg: Rc<RefCell<LocalCfg>>) -> Self {
-        Self{transactions, accounts, tags, cfg}
+        Self{transactions, accounts, tags, cfg, period: Period::Month, date: Date::today(), selection: 0}
     }
 
-    fn display_transaction(transaction: &Transaction, element_box: TermBox, active: bool, cfg_data: &LocalCfg, tags_data: &Tags, accounts_data: &Accounts, transactions_data: &Transactions) -> crossterm::Result<()> {
+    /// Tags with activity in either period then accounts, under a title row without totals.
+    fn rows(&self) -> Vec<(String, Option<(Totals, Totals)>)> {
+        let tags = self.tags.borrow();
+        let account = self.cfg.borrow().account().clone();
+        let (current, previous) = Report::with_previous(self.period, &self.date, self.transactions.borrow().transactions(), &tags, &self.accounts.borrow(), &account);
+
+        let mut ret = vec![("Tags".to_string(), None)];
+        for tag in tags.tree(&BTreeSet::new()) {
+            let (current, previous) = (current.tags[&tag], previous.tags[&tag]);
+            if current != Totals::zero() || previous != Totals::zero() {
+                ret.push((format!("{}{}", "  ".repeat(tags.depth(&tag) + 1), tag), Some((current, previous))));
+            }
+        }
+        ret.push(("Accounts".to_string(), None));
+        for (account, totals) in current.accounts {
+            let previous = previous.accounts[&account];
+            ret.push((format!("  {account}"), Some((totals, previous))));
+        }
+        ret
+    }
+}
+
+impl TermElement for ReportTE {
+    fn display(&self, element_box: TermBox, _active: bool) -> crossterm::Result<()> {
         use crossterm::{
             queue,
-            style::{Print, PrintStyledContent, Color, StyledContent},
+            style::{Print, PrintStyledContent, Color},
         };
 
-        assert_eq!(element_box.height(), 1);
-
-        let date_cf = (Date::STRING_WIDTH, 0);
-        let amount_cf = (8, 1);
+        let height = element_box.height();
+        let cfg = self.cfg.borrow();
+        let amount_cf = (7, 1);
         let space_cf = (1, 0);
-        let desc_cf = (6, 20);
-        let kind_cf = (6, 4);
-        let accounts_cf = (6, 4);
-
-        let [date_width, _, kind_width, _, desc_width, _, accounts_width, _, internal_delta_width, _, external_delta_width, _, internal_flow_width] = subdiv_const_flex(element_box.width(), [date_cf, space_cf, kind_cf, space_cf, desc_cf, space_cf, accounts_cf, space_cf, amount_cf, space_cf, amount_cf, space_cf, amount_cf]);
-
-        assert_eq!(date_width, Date::STRING_WIDTH);
+        let weights = [(8, 4), space_cf, amount_cf, space_cf, amount_cf, space_cf, amount_cf, space_cf, amount_cf, space_cf, amount_cf, space_cf, amount_cf, space_cf, amount_cf, space_cf, amount_cf];
+        if height < 5 || element_box.width() <= weights.iter().map(|(c, _)| c).sum::<usize>() {
+            element_box.begin().goto()?;
+            queue!(stdout(), Print("..."))?;
+            return Ok(());
+        }
 
         element_box.begin().goto()?;
+        let title = format!("{} compared to {} (Left/Right: period, Tab: month/year)", self.period.label(&self.date), self.period.label(&self.period.previous(&self.date)));
+        queue!(stdout(), Print(truncate_align_left(&title, element_box.width())))?;
+
+        let widths = subdiv_const_flex(element_box.width(), weights);
+        let name_width = widths[0];
+        let amount_widths: Vec<usize> = widths.iter().skip(2).step_by(2).cloned().collect();
+
+        TermPos::new(element_box.left, element_box.top + 1).goto()?;
+        let headers = ["Spent", "Change", "Internal", "Change", "External", "Change", "Flow", "Change"];
+        queue!(stdout(), PrintStyledContent(simple_stylize(truncate_align_center("", name_width), Color::Reset, true, false)))?;
+        for (header, width) in headers.iter().zip(&amount_widths) {
+            queue!(stdout(), Print(" "), PrintStyledContent(simple_stylize(truncate_align_center(header, *width), Color::Reset, true, false)))?;
+        }
 
-        let space = simple_stylize(" ", Color::Reset, true, active);
-
-        let date = simple_stylize(transaction.date().to_string(), Color::Reset, true, active);
+        let rows = self.rows();
+        let list_height = height - 2;
+        let begin_index = (self.selection + 1).saturating_sub(list_height);
 
-        fn stylize_amount(amount: SignedCentsAmount, currency: &str, width: usize, active: bool) -> StyledContent<String> {
-            assert!(width>currency.chars().count());
-            use std::cmp::Ordering::*;
-            let color = match amount.cents().cmp(&0) {
-                Less => Color::Red,
-                Equal => Color::Reset,
-                Greater => Color::Green,
+        for (index, (name, totals)) in rows.iter().enumerate().skip(begin_index).take(list_height) {
+            let active = index == self.selection;
+            TermPos::new(element_box.left, element_box.top + 2 + index - begin_index).goto()?;
+            queue!(stdout(), PrintStyledContent(simple_stylize(truncate_align_left(name, name_width), Color::Reset, true, active)))?;
+            let Some((current, previous)) = totals else {
+                continue;
             };
-            let bold = amount.cents() != 0;
-            simple_stylize(amount.as_string_width_padded(width-currency.chars().count(), false)+currency, color, bold, active)
-        }
-
-        fn stylize_account(account: AccountRef, short: bool, active: bool, accounts_data: &Accounts) -> StyledContent<String> {
-            let color: Color = accounts_data.0.get(&account).unwrap().color().into();
-            if short {
-                simple_stylize(account[0..1].to_string(), color, true, active)
-            } else {
-                simple_stylize(account, color, true, active)
-            }
-        }
 
-        fn stylize_accounts(accounts: Vec<AccountRef>, width: usize, active: bool, accounts_data: &Accounts) -> String {
-            if accounts.is_empty() {
-                return simple_stylize(" ".repeat(width), Color::Reset, true, active).to_string();
-            }
-            let width_spaces = accounts.len() - 1;
-            let width_large = accounts.iter().map(|x| x.len()).sum::<usize>() + width_spaces;
-            let width_short = accounts.len() + width_spaces;
-            if width >= width_short {
-                let short = width < width_large;
-                let rem_width = width - (if short {width_short} else {width_large});
-                let mut ret = String::new();
-                let mut iter = accounts.into_iter();
-                ret += &simple_stylize(" ".repeat(rem_width/2), Color::Reset, true, active).to_string();
-                ret += &stylize_account(iter.next().unwrap(), short, active, accounts_data).to_string();
-                for account in iter {
-                    ret += &simple_stylize(" ", Color::Reset, true, active).to_string();
-                    ret += &stylize_account(account, short, active, accounts_data).to_string();
-                }
-                ret += &simple_stylize(" ".repeat((rem_width+1)/2), Color::Reset, true, active).to_string();
-                ret
-            } else {
-                simple_stylize(format!("{: ^width$}", "...", width = width), Color::Reset, true, active).to_string()
+            // Spending is shown negative, so that an increase shows in red like the deltas.
+            let [spent, internal, external, flow] = current.changes(previous);
+            let negate = |amount: SignedCentsAmount| SignedCentsAmount::new(-amount.cents());
+            let cells = [
+                (SignedCentsAmount::negative(current.spent), Unit::Currency),
+                (negate(spent), Unit::Currency),
+                (current.internal, Unit::Currency),
+                (internal, Unit::Currency),
+                (current.external, Unit::Currency),
+                (external, Unit::Currency),
+                (SignedCentsAmount::negative(current.flow), Unit::Flow),
+                (negate(flow), Unit::Flow),
+            ];
+            for ((amount, unit), width) in cells.into_iter().zip(&amount_widths) {
+                queue!(stdout(), PrintStyledContent(simple_stylize(" ".to_string(), Color::Reset, true, active)), PrintStyledContent(stylize_amount(amount, unit, *width, active, cfg.format())))?;
             }
         }
 
-        let kind = simple_stylize(truncate_align_left(&transaction.kind_str(), kind_width), Color::Reset, true, active);
-        let desc = simple_stylize(truncate_align_left(transaction.desc(), desc_width), Color::Reset, true, active);
-        let accounts = stylize_accounts(transaction.accounts(), accounts_width, active, accounts_data);
+        Ok(())
+    }
 
-        let int_amount = stylize_amount(transaction.internal_delta(&cfg_data.account), "€", internal_delta_width, active);
-        let ext_amount = stylize_amount(transaction.external_delta(&cfg_data.account), "€", external_delta_width, active);
+    fn popup(&self, _element_box: TermBox, _window_box: TermBox) -> crossterm::Result<()> {
+        Ok(())
+    }
 
-        let flow = stylize_amount(transaction.internal_flow(&cfg_data.account, tags_data, transactions_data).0, "¤", internal_flow_width, active);
+    fn set_cursor(&self, _element_box: TermBox, _window_box: TermBox) -> crossterm::Result<()> {
+        use crossterm::{queue, cursor};
+        queue!(stdout(), cursor::Hide)
+    }
 
-        queue!(stdout(), PrintStyledContent(date), PrintStyledContent(space), PrintStyledContent(kind), PrintStyledContent(space), PrintStyledContent(desc), PrintStyledContent(space), Print(accounts), PrintStyledContent(space), PrintStyledContent(int_amount), PrintStyledContent(space), PrintStyledContent(ext_amount), PrintStyledContent(space), PrintStyledContent(flow))?;
+    fn input(&mut self, event: InputEvent) -> Option<InputEvent> {
+        use InputEvent::*;
 
-        Ok(())
+        match event {
+            Up => {
+                self.selection = self.selection.saturating_sub(1);
+                None
+            },
+            Down => {
+                if self.selection + 1 < self.rows().len() {
+                    self.selection += 1;
+                }
+                None
+            },
+            Left => {
+                self.date = self.period.previous(&self.date);
+                None
+            },
+            Right => {
+                self.date = self.period.next(&self.date);
+                None
+            },
+            Tab => {
+                self.period = self.period.toggle();
+                None
+            },
+            _ => Some(event),
+        }
     }
+}
 
-    fn display_transaction_header(element_box: TermBox) -> crossterm::Result<()> {
-        use crossterm::{
-            queue,
-            style::{PrintStyledContent, Color},
-        };
 
-        assert_eq!(element_box.height(), 1);
 
-        let date_cf = (Date::STRING_WIDTH, 0);
-        let amount_cf = (8, 1);
-        let space_cf = (1, 0);
-        let desc_cf = (6, 20);
-        let kind_cf = (6, 4);
-        let accounts_cf = (6, 4);
+#[derive(Clone)]
+pub struct GraphTE {
+    transactions: Rc<RefCell<InteractiveTransactions>>,
+    tags: Rc<RefCell<Tags>>,
+    cfg: Rc<RefCell<LocalCfg>>,
+}
 
-        let [date_width, _, kind_width, _, desc_width, _, accounts_width, _, internal_delta_width, _, external_delta_width, _, internal_flow_width] = subdiv_const_flex(element_box.width(), [date_cf, space_cf, kind_cf, space_cf, desc_cf, space_cf, accounts_cf, space_cf, amount_cf, space_cf, amount_cf, space_cf, amount_cf]);
+const GRAPH_COLORS: [crossterm::style::Color; 6] = {
+    use crossterm::style::Color::*;
+    [Red, Green, Yellow, Blue, Magenta, Cyan]
+};
 
-        element_box.begin().goto()?;
+const GRAPH_BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
 
-        let space = simple_stylize(" ", Color::Reset, true, false);
+impl GraphTE {
+    pub fn new(transactions: Rc<RefCell<InteractiveTransactions>>, tags: Rc<RefCell<Tags>>, cfg: Rc<RefCell<LocalCfg>>) -> Self {
+        Self{transactions, tags, cfg}
+    }
 
-        let date = simple_stylize(truncate_align_center("Date", date_width), Color::Reset, true, false);
-        let kind = simple_stylize(truncate_align_center("Kind", kind_width), Color::Reset, true, false);
-        let desc = simple_stylize(truncate_align_center("Description", desc_width), Color::Reset, true, false);
-        let accounts = simple_stylize(truncate_align_center("Accounts", accounts_width), Color::Reset, true, false);
-        let int_amount = simple_stylize(truncate_align_center("Internal", internal_delta_width), Color::Reset, true, false);
-        let ext_amount = simple_stylize(truncate_align_center("External", external_delta_width), Color::Reset, true, false);
-        let flow = simple_stylize(truncate_align_center("Flow", internal_flow_width), Color::Reset, true, false);
+    /// Daily flow of each root tag, in eighths of cells, from `from` on.
+    fn columns(&self, from: &Date, days: usize, roots: &[TagRef], height: usize) -> (Vec<Vec<CentsAmount>>, Vec<Vec<usize>>) {
+        let tags_data = self.tags.borrow();
+        let transactions = self.transactions.borrow();
+        let account = self.cfg.borrow().account().clone();
 
-        queue!(stdout(), PrintStyledContent(date), PrintStyledContent(space), PrintStyledContent(kind), PrintStyledContent(space), PrintStyledContent(desc), PrintStyledContent(space), PrintStyledContent(accounts), PrintStyledContent(space), PrintStyledContent(int_amount), PrintStyledContent(space), PrintStyledContent(ext_amount), PrintStyledContent(space), PrintStyledContent(flow))?;
+        let mut to = from.clone();
+        for _ in 1..days {
+            to.incr();
+        }
 
-        Ok(())
+        let amounts: Vec<Vec<CentsAmount>> = transactions.transactions().daily_flows(from, &to, &account, &tags_data).iter().map(|flows| {
+            let rolled = tags_data.rollup(flows, CentsAmount::new(0));
+            roots.iter().map(|root| rolled[root]).collect()
+        }).collect();
+
+        let max = amounts.iter().map(|day| day.iter().map(|x| x.cents()).sum::<u64>()).max().unwrap_or(0);
+        let eighths = amounts.iter().map(|day| {
+            let mut acc = 0;
+            day.iter().map(|x| {
+                let prev = (acc * 8 * height as u64).checked_div(max).unwrap_or(0);
+                acc += x.cents();
+                let next = (acc * 8 * height as u64).checked_div(max).unwrap_or(0);
+                (next - prev) as usize
+            }).collect()
+        }).collect();
+
+        (amounts, eighths)
     }
 }
 
-impl TermElement for TransactionsTE {
+impl TermElement for GraphTE {
     fn display(&self, element_box: TermBox, _active: bool) -> crossterm::Result<()> {
-        use crossterm::{queue, style::Print};
+        use crossterm::{
+            queue,
+            style::{Print, PrintStyledContent, Color, Stylize},
+        };
+
         let height = element_box.height();
-        if height < 5 {
+        let cfg = self.cfg.borrow();
+        let width = element_box.width();
+        if height < 5 || width < 2 * cfg.format().date_width() + 2 {
             element_box.begin().goto()?;
             queue!(stdout(), Print("..."))?;
             return Ok(());
         }
-        let list_height = height - 1;
-
-        let center_index = self.transactions.borrow().selection;
-        let mut begin_index = center_index;
-        let mut end_index = center_index;
+        let bar_height = height - 2;
 
-        while end_index - begin_index < list_height {
-            let avail_begin = begin_index > 0;
-            let avail_end = end_index < self.transactions.borrow().transactions().len();
+        let roots = self.tags.borrow().children(None);
+        let cursor = self.transactions.borrow().selected().map_or_else(Date::today, |tr| tr.date().clone());
+        let mut from = cursor.clone();
+        for _ in 0..width/2 {
+            from.decr();
+        }
+        let (amounts, eighths) = self.columns(&from, width, &roots, bar_height);
 
-            match (avail_begin, avail_end) {
-                (true, true) if center_index - begin_index < end_index - center_index => begin_index -= 1,
-                (true, true) => end_index += 1,
-                (true, false) => begin_index -= 1,
-                (false, true) => end_index += 1,
-                (false, false) => break,
+        element_box.begin().goto()?;
+        let mut legend_width = 0;
+        for (index, root) in roots.iter().enumerate() {
+            let item = format!("■ {root} ");
+            legend_width += item.chars().count();
+            if legend_width > width {
+                break;
             }
+            queue!(stdout(), PrintStyledContent(simple_stylize(item, GRAPH_COLORS[index % GRAPH_COLORS.len()], true, false)))?;
         }
 
-        let header_box = TermBox{left: element_box.left, right: element_box.right, top: element_box.top, bottom: element_box.top+1};
-        Self::display_transaction_header(header_box)?;
+        for (col, segments) in eighths.iter().enumerate() {
+            let is_cursor = col == width/2;
+            for row in 0..bar_height {
+                let bottom = row * 8;
+                let total: usize = segments.iter().sum();
+                let cell = if total > bottom {
+                    let filled = usize::min(total - bottom, 8);
+                    let top = bottom + filled - 1;
+                    let mut acc = 0;
+                    let index = segments.iter().position(|x| { acc += x; acc > top }).unwrap_or(0);
+                    (GRAPH_BLOCKS[filled - 1], GRAPH_COLORS[index % GRAPH_COLORS.len()])
+                } else {
+                    (' ', Color::Reset)
+                };
+
+                TermPos::new(element_box.left + col, element_box.bottom - 2 - row).goto()?;
+                let styled = cell.0.with(cell.1);
+                if is_cursor {
+                    queue!(stdout(), PrintStyledContent(styled.on(Color::DarkGrey)))?;
+                } else {
+                    queue!(stdout(), PrintStyledContent(styled))?;
+                }
+            }
+        }
 
-        for (index, transaction) in self.transactions.borrow().transactions().vec()[begin_index..end_index].iter().enumerate() {
-            let trans_selected = begin_index + index == self.transactions.borrow().selection;
-            let trans_box = TermBox{left: element_box.left, right: element_box.right, top: element_box.top+index+1, bottom: element_box.top+index+2};
-            Self::display_transaction(&transaction, trans_box, trans_selected, &self.cfg.borrow(), &self.tags.borrow(), &self.accounts.borrow(), &self.transactions.borrow().transactions)?;
+        let mut to = from.clone();
+        for _ in 1..width {
+            to.incr();
         }
+        let total = amounts.get(width/2).map_or(CentsAmount::new(0), |day| day.iter().fold(CentsAmount::new(0), |acc, x| acc + *x));
+        let format = cfg.format();
+        let label = format!("{} {}", format.date(&cursor), format.with_symbol(&total.as_string_exact(format.marks(true)), Unit::Flow));
+        let [left_width, center_width, right_width] = subdiv_const_flex(width, [(format.date_width(), 0), (1, 1), (format.date_width(), 0)]);
+        TermPos::new(element_box.left, element_box.bottom - 1).goto()?;
+        queue!(stdout(),
+            Print(truncate_align_left(&format.date(&from), left_width)),
+            PrintStyledContent(simple_stylize(truncate_align_center(&label, center_width), Color::Reset, true, false)),
+            Print(truncate_align_left(&format.date(&to), right_width)),
+        )?;
 
         Ok(())
     }
@@ -931,18 +2777,14 @@ impl TermElement for TransactionsTE {
         use InputEvent::*;
 
         match event {
-            Up => {
+            Up | Left => {
                 self.transactions.borrow_mut().prev();
                 None
             },
-            Down => {
+            Down | Right => {
                 self.transactions.borrow_mut().next();
                 None
             },
-            Char('d') => {
-                self.transactions.borrow_mut().remove();
-                None
-            },
             _ => Some(event),
         }
     }
@@ -950,57 +2792,191 @@ impl TermElement for TransactionsTE {
 
 
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum AppView {
+    Transactions,
+    Balances,
+    Tags,
+    Budgets,
+    Report,
+    Graph,
+}
+
 #[derive(Clone)]
 pub struct AppContent {
+    dir: LedgerDir,
+    cfg: Rc<RefCell<LocalCfg>>,
     tags: Rc<RefCell<Tags>>,
     accounts: Rc<RefCell<Accounts>>,
+    rates: Rates,
     transactions: Rc<RefCell<InteractiveTransactions>>,
     transactions_menu: TransactionsTE,
-    purchase: Option<PurchaseInput>,
+    balances_menu: BalancesTE,
+    tags_menu: TagsTE,
+    budgets_menu: BudgetsTE,
+    report_menu: ReportTE,
+    graph_menu: GraphTE,
+    view: AppView,
+    input: Option<TransactionInput>,
+    editing: bool,
+    status: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LocalCfg {
-    account: AccountRef
+    account: AccountRef,
+    #[serde(default)]
+    format: Formatting,
+}
+
+impl LocalCfg {
+    pub fn new(account: AccountRef) -> Self {
+        Self{account, format: Formatting::default()}
+    }
+
+    pub fn account(&self) -> &AccountRef {
+        &self.account
+    }
+
+    pub fn format(&self) -> &Formatting {
+        &self.format
+    }
+
+    /// Reads the local config of the ledger and checks its formatting.
+    pub fn load(dir: &LedgerDir) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut cfg: Self = Self::read_yaml(dir.file(LOCALCFG_FILE))?;
+        cfg.format.init()?;
+        Ok(cfg)
+    }
 }
 
 impl YamlRW for LocalCfg {}
 
 impl AppContent {
-    pub fn new() -> Self {
-        let cfg = LocalCfg::read_yaml("localcfg.yaml").unwrap();
+    pub fn new(dir: LedgerDir) -> Result<Self, Box<dyn std::error::Error>> {
+        let cfg = LocalCfg::load(&dir)?;
         let cfg = Rc::new(RefCell::new(cfg));
 
-        let mut tags = Tags::read_yaml("tags.yaml").unwrap();
-        tags.fix();
+        let mut tags = Tags::read_yaml(dir.file(TAGS_FILE))?;
+        tags.fix()?;
         let tags = Rc::new(RefCell::new(tags));
 
-        let accounts = Accounts::read_yaml("accounts.yaml").unwrap();
+        let accounts = Accounts::read_yaml(dir.file(ACCOUNTS_FILE))?;
         let accounts = Rc::new(RefCell::new(accounts));
 
-        let mut transactions = Transactions::read_yaml("data.yaml").unwrap_or_else(|_| Transactions::new());
-        transactions.fix();
-        let transactions = Rc::new(RefCell::new(InteractiveTransactions::new(transactions)));
-
-        Self{tags: Rc::clone(&tags), accounts: Rc::clone(&accounts), transactions: Rc::clone(&transactions), transactions_menu: TransactionsTE::new(transactions, accounts, tags, cfg), purchase: None}
+        let mut transactions = Transactions::load(&dir)?;
+        let rates = currency::load(&dir)?;
+        let (generated, mut missing) = recurring::materialize(&dir, &tags.borrow(), &accounts.borrow(), &rates, &mut transactions)?;
+        missing.extend(rates.missing(&transactions));
+        let status = currency::missing_warning(&missing).or_else(|| (generated > 0).then(|| format!("{generated} recurring transaction(s) added")));
+        let transactions = Rc::new(RefCell::new(InteractiveTransactions::new(transactions, dir.file(DATA_FILE))));
+
+        Ok(Self{
+            dir,
+            cfg: Rc::clone(&cfg),
+            tags: Rc::clone(&tags),
+            accounts: Rc::clone(&accounts),
+            rates,
+            transactions: Rc::clone(&transactions),
+            transactions_menu: TransactionsTE::new(Rc::clone(&transactions), Rc::clone(&accounts), Rc::clone(&tags), Rc::clone(&cfg)),
+            balances_menu: BalancesTE::new(Rc::clone(&transactions), Rc::clone(&accounts), Rc::clone(&cfg)),
+            tags_menu: TagsTE::new(Rc::clone(&transactions), Rc::clone(&tags), Rc::clone(&cfg)),
+            budgets_menu: BudgetsTE::new(Rc::clone(&transactions), Rc::clone(&tags), Rc::clone(&cfg)),
+            report_menu: ReportTE::new(Rc::clone(&transactions), accounts, Rc::clone(&tags), Rc::clone(&cfg)),
+            graph_menu: GraphTE::new(transactions, tags, cfg),
+            view: AppView::Transactions,
+            input: None,
+            editing: false,
+            status,
+        })
+    }
+
+    /// Warning about the budgets exceeded by `transaction` when it falls in the current month.
+    fn budget_alert(&self, transaction: &Transaction) -> Option<String> {
+        let Transaction::Purchase(purchase) = transaction else {
+            return None;
+        };
+        let today = Date::today();
+        if purchase.date.first_of_month() != today.first_of_month() {
+            return None;
+        }
+        let tags = self.tags.borrow();
+        let statuses = budget::statuses(self.transactions.borrow().transactions(), &tags, self.cfg.borrow().account(), &today);
+        budget::alert(&statuses, &purchase.tag, &tags, self.cfg.borrow().format())
+    }
+
+    /// Draws the flow and balance charts of the whole history next to the data file.
+    fn write_chart(&self) -> String {
+        let path = self.dir.file(CHART_FILE);
+        let transactions = self.transactions.borrow();
+        let options = ChartOptions::new(transactions.transactions());
+        let result = chart::draw(&path, transactions.transactions(), &self.tags.borrow(), &self.accounts.borrow(), self.cfg.borrow().account(), &options, self.cfg.borrow().format());
+        match result {
+            Ok(()) => format!("Chart written to {}", path.display()),
+            Err(err) => format!("Failed to write chart: {err}"),
+        }
     }
 
     fn new_purchase(&mut self, date: Date) {
+        let currency_completor = Completor::new(self.rates.currencies());
+        let desc_completor = Completor::new(self.transactions.borrow().transactions().purchase_descriptions(&Date::today()));
+        let tag_completor = Completor::new(self.tags.borrow().clone().0.into_keys().collect());
+        let account_completor = Completor::new(self.accounts.borrow().clone().0.into_keys().collect());
+
+        let mut input = PurchaseInput::new(date, currency_completor, desc_completor, tag_completor, account_completor, Rc::clone(&self.cfg));
+        input.set_templates(self.transactions.borrow().transactions().latest_purchases());
+        self.input = Some(TransactionInput::Purchase(Box::new(input)));
+        self.editing = false;
+    }
+
+    fn new_transfer(&mut self, date: Date) {
         let desc_completor = Completor::new(Vec::new());
+        let account_completor = Completor::new(self.accounts.borrow().clone().0.into_keys().collect());
+
+        self.input = Some(TransactionInput::Transfer(Box::new(TransferInput::new(date, desc_completor, account_completor, Rc::clone(&self.cfg)))));
+        self.editing = false;
+    }
+
+    fn edit_selected(&mut self) {
+        let currency_completor = Completor::new(self.rates.currencies());
+        let desc_completor = Completor::new(self.transactions.borrow().transactions().purchase_descriptions(&Date::today()));
         let tag_completor = Completor::new(self.tags.borrow().clone().0.into_keys().collect());
         let account_completor = Completor::new(self.accounts.borrow().clone().0.into_keys().collect());
 
-        self.purchase = Some(PurchaseInput::new(date, desc_completor, tag_completor, account_completor));
+        let input = match self.transactions.borrow().selected() {
+            Some(Transaction::Purchase(purchase)) => TransactionInput::Purchase(Box::new(PurchaseInput::from_purchase(purchase, currency_completor, desc_completor, tag_completor, account_completor, Rc::clone(&self.cfg)))),
+            Some(Transaction::Transfer(transfer)) => TransactionInput::Transfer(Box::new(TransferInput::from_transfer(transfer, desc_completor, account_completor, Rc::clone(&self.cfg)))),
+            None => return,
+        };
+
+        self.input = Some(input);
+        self.editing = true;
     }
 
     fn child_box(&self, element_box: TermBox) -> TermBox {
         TermBox{left: element_box.left, right: element_box.right, top: element_box.top+2, bottom: element_box.bottom}
     }
-}
 
-impl Drop for AppContent {
-    fn drop(&mut self) {
-        self.transactions.borrow_mut().transactions().write_yaml("data.yaml").unwrap();
+    fn menu(&self) -> &dyn TermElement {
+        match self.view {
+            AppView::Transactions => &self.transactions_menu,
+            AppView::Balances => &self.balances_menu,
+            AppView::Tags => &self.tags_menu,
+            AppView::Budgets => &self.budgets_menu,
+            AppView::Report => &self.report_menu,
+            AppView::Graph => &self.graph_menu,
+        }
+    }
+
+    fn menu_mut(&mut self) -> &mut dyn TermElement {
+        match self.view {
+            AppView::Transactions => &mut self.transactions_menu,
+            AppView::Balances => &mut self.balances_menu,
+            AppView::Tags => &mut self.tags_menu,
+            AppView::Budgets => &mut self.budgets_menu,
+            AppView::Report => &mut self.report_menu,
+            AppView::Graph => &mut self.graph_menu,
+        }
     }
 }
 
@@ -1012,14 +2988,22 @@ impl TermElement for AppContent {
         };
 
         element_box.begin().goto()?;
-        queue!(stdout(), Print("Hello"))?;
+        match self.dir.name() {
+            Some(name) => queue!(stdout(), Print(format!("Hello ({name})")))?,
+            None => queue!(stdout(), Print("Hello"))?,
+        }
 
-        match &self.purchase {
-            Some(purchase) => {
-                purchase.display(self.child_box(element_box), true)?;
+        if let Some(status) = &self.status {
+            TermPos::new(element_box.left, element_box.top+1).goto()?;
+            queue!(stdout(), Print(truncate_align_left(status, element_box.width())))?;
+        }
+
+        match &self.input {
+            Some(input) => {
+                input.display(self.child_box(element_box), true)?;
             },
             None => {
-                self.transactions_menu.display(self.child_box(element_box), true)?;
+                self.menu().display(self.child_box(element_box), true)?;
             },
         }
 
@@ -1027,44 +3011,146 @@ impl TermElement for AppContent {
     }
 
     fn popup(&self, element_box: TermBox, window_box: TermBox) -> crossterm::Result<()> {
-        match &self.purchase {
-            Some(purchase) => purchase.popup(self.child_box(element_box), window_box),
-            None => self.transactions_menu.popup(self.child_box(element_box), window_box),
+        match &self.input {
+            Some(input) => input.popup(self.child_box(element_box), window_box),
+            None => self.menu().popup(self.child_box(element_box), window_box),
         }
     }
 
     fn set_cursor(&self, element_box: TermBox, window_box: TermBox) -> crossterm::Result<()> {
-        match &self.purchase {
-            Some(purchase) => purchase.set_cursor(self.child_box(element_box), window_box),
-            _ => self.transactions_menu.set_cursor(self.child_box(element_box), window_box),
+        match &self.input {
+            Some(input) => input.set_cursor(self.child_box(element_box), window_box),
+            _ => self.menu().set_cursor(self.child_box(element_box), window_box),
         }
     }
 
     fn input(&mut self, event: InputEvent) -> Option<InputEvent> {
+        self.status = None;
+
+        let ret = self.handle_input(event);
+
+        if let Some(err) = self.transactions.borrow_mut().take_save_error() {
+            self.status = Some(err);
+        }
+
+        ret
+    }
+}
+
+impl AppContent {
+    fn handle_input(&mut self, event: InputEvent) -> Option<InputEvent> {
         use InputEvent::*;
 
-        match &mut self.purchase {
-            Some(purchase) => {
-                match purchase.input(event) {
+        match &mut self.input {
+            Some(input) => {
+                match input.input(event) {
+                    Some(Tab | Enter) if self.editing => {
+                        let transaction = match self.rates.converted(input.clone().into()) {
+                            Ok(transaction) => transaction,
+                            Err(err) => {
+                                self.status = Some(err.to_string());
+                                return None;
+                            },
+                        };
+                        if self.transactions.borrow_mut().replace(transaction.clone()).is_none() {
+                            self.status = Some("The edited transaction no longer exists".to_string());
+                        } else {
+                            self.status = self.budget_alert(&transaction);
+                        }
+                        self.input = None;
+                        self.editing = false;
+                        None
+                    },
                     Some(Tab | Enter) => {
-                        let date = purchase.date.date.clone();
-                        self.transactions.borrow_mut().add(Transaction::Purchase(purchase.clone().into()));
-                        self.new_purchase(date);
+                        let date = input.date();
+                        let transaction = match self.rates.converted(input.clone().into()) {
+                            Ok(transaction) => transaction,
+                            Err(err) => {
+                                self.status = Some(err.to_string());
+                                return None;
+                            },
+                        };
+                        self.transactions.borrow_mut().add(transaction.clone());
+                        self.status = self.budget_alert(&transaction);
+                        match transaction {
+                            Transaction::Purchase(_) => self.new_purchase(date),
+                            Transaction::Transfer(_) => self.new_transfer(date),
+                        }
                         None
                     },
                     Some(Esc) => {
-                        self.purchase = None;
+                        self.input = None;
+                        self.editing = false;
                         None
                     },
                     event_opt => event_opt,
                 }
             },
             None => {
-                match self.transactions_menu.input(event) {
+                match self.menu_mut().input(event) {
                     Some(Char('i')) => {
                         self.new_purchase(Date::today());
                         None
                     },
+                    Some(Char('t')) => {
+                        self.new_transfer(Date::today());
+                        None
+                    },
+                    Some(Char('e') | Enter) => {
+                        self.edit_selected();
+                        None
+                    },
+                    Some(Char('u')) => {
+                        self.status = Some(self.transactions.borrow_mut().undo());
+                        None
+                    },
+                    Some(Ctrl('r')) => {
+                        self.status = Some(self.transactions.borrow_mut().redo());
+                        None
+                    },
+                    Some(Char('p')) => {
+                        self.status = Some(self.write_chart());
+                        None
+                    },
+                    Some(Esc) if self.view != AppView::Transactions => {
+                        self.view = AppView::Transactions;
+                        None
+                    },
+                    Some(Char('b')) => {
+                        self.view = match self.view {
+                            AppView::Balances => AppView::Transactions,
+                            _ => AppView::Balances,
+                        };
+                        None
+                    },
+                    Some(Char('g')) => {
+                        self.view = match self.view {
+                            AppView::Tags => AppView::Transactions,
+                            _ => AppView::Tags,
+                        };
+                        None
+                    },
+                    Some(Char('B')) => {
+                        self.view = match self.view {
+                            AppView::Budgets => AppView::Transactions,
+                            _ => AppView::Budgets,
+                        };
+                        None
+                    },
+                    Some(Char('r')) => {
+                        self.view = match self.view {
+                            AppView::Report => AppView::Transactions,
+                            _ => AppView::Report,
+                        };
+                        None
+                    },
+                    Some(Char('f')) => {
+                        self.view = match self.view {
+                            AppView::Graph => AppView::Transactions,
+                            _ => AppView::Graph,
+                        };
+                        None
+                    },
                     event_opt => event_opt,
                 }
             },
@@ -1077,8 +3163,8 @@ impl TermElement for AppContent {
 pub struct App(AppContent);
 
 impl App {
-    pub fn new() -> Self {
-        Self(AppContent::new())
+    pub fn new(dir: LedgerDir) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self(AppContent::new(dir)?))
     }
 }
 
@@ -1104,3 +3190,56 @@ impl TermElement for App {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transfer(desc: &str) -> Transaction {
+        Transaction::Transfer(Transfer{date: Date::today(), from: "alice".to_string(), to: "bob".to_string(), amount: CentsAmount::new(1000), desc: desc.to_string()})
+    }
+
+    fn history(name: &str) -> InteractiveTransactions {
+        let dir = std::env::temp_dir().join(format!("expenses-test-{name}-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        InteractiveTransactions::new(Transactions::new(), dir.join("data.yaml"))
+    }
+
+    #[test]
+    fn amount_expression() {
+        let mut input = AmountInput::new(Rc::new(RefCell::new(LocalCfg::new("alice".to_string()))));
+        for c in "12.50+3.20*2".chars() {
+            assert!(input.input(InputEvent::Char(c)).is_none());
+        }
+        assert_eq!(input.amount(), Some(CentsAmount::new(1890)));
+        assert!(matches!(input.input(InputEvent::Tab), Some(InputEvent::Tab)));
+        assert_eq!(CentsAmount::from(input), CentsAmount::new(1890));
+    }
+
+    #[test]
+    fn undo_redo() {
+        let mut history = history("undo");
+        history.add(transfer("rent"));
+        history.replace(transfer("rent, corrected"));
+        assert_eq!(history.undo(), "Undone: edit of \"rent\"");
+        assert_eq!(history.selected(), Some(&transfer("rent")));
+        assert_eq!(history.undo(), "Undone: add \"rent\"");
+        assert_eq!(history.transactions().len(), 0);
+        assert_eq!(history.undo(), "Nothing to undo");
+        assert_eq!(history.redo(), "Redone: add \"rent\"");
+        assert_eq!(history.redo(), "Redone: edit of \"rent\"");
+        assert_eq!(history.selected(), Some(&transfer("rent, corrected")));
+        std::fs::remove_dir_all(history.path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn undo_missing_transaction() {
+        let mut history = history("missing");
+        history.add(transfer("rent"));
+        history.transactions.remove(0);
+        assert_eq!(history.undo(), "Cannot undo add \"rent\", the transaction was not found");
+        assert_eq!(history.undo(), "Nothing to undo");
+        assert_eq!(history.replace(transfer("rent")), None);
+        std::fs::remove_dir_all(history.path.parent().unwrap()).unwrap();
+    }
+}
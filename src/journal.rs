@@ -0,0 +1,424 @@
+use std::error;
+use std::fmt;
+use std::io::Write;
+use std::collections::BTreeMap;
+use chrono::NaiveDate;
+
+use crate::tags::{Tags, TagRef};
+use crate::accounts::{Accounts, AccountRef};
+use crate::transaction::{Transaction, Purchase, Transfer, Consumers};
+use crate::money::*;
+use crate::currency::ForeignAmount;
+use crate::datetime::Date;
+
+/// Purchases are written as one posting per consumer under `Expenses:<consumer>:<tag path>`,
+/// funded by `Assets:<buyer>`, and transfers as a move between two `Assets` accounts.
+const EXPENSES: &str = "Expenses";
+const ASSETS: &str = "Assets";
+
+#[derive(Debug)]
+pub enum Error {
+    Syntax(usize, String),
+    UnknownTag(usize, TagRef),
+    UnknownAccount(usize, AccountRef),
+    Unsupported(usize),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Syntax(line, ref msg) =>
+                write!(f, "Line {line}: {msg}"),
+            Error::UnknownTag(line, ref tag) =>
+                write!(f, "Line {line}: unknown tag \"{tag}\""),
+            Error::UnknownAccount(line, ref account) =>
+                write!(f, "Line {line}: unknown account \"{account}\""),
+            Error::Unsupported(line) =>
+                write!(f, "Line {line}: transaction is neither a purchase nor a transfer between accounts"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+fn tag_path(tag: &TagRef, tags: &Tags) -> String {
+    let mut path = tags.ancestors(tag);
+    path.reverse();
+    path.push(tag.clone());
+    path.join(":")
+}
+
+fn amount_string(amount: SignedCentsAmount, commodity: &str) -> String {
+    format!("{} {commodity}", amount.as_decimal())
+}
+
+fn write_purchase<W: Write>(out: &mut W, purchase: &Purchase, tags: &Tags, commodity: &str) -> std::io::Result<()> {
+    let path = tag_path(&purchase.tag, tags);
+    let amounts = purchase.consumers.amounts(purchase.amount);
+    for (consumer, weight) in &purchase.consumers.0 {
+        let account = format!("{EXPENSES}:{consumer}:{path}");
+        writeln!(out, "    {account:<40} {:>12}  ; weight: {weight}", amount_string(SignedCentsAmount::positive(amounts[consumer]), commodity))?;
+    }
+    writeln!(out, "    {:<40} {:>12}", format!("{ASSETS}:{}", purchase.buyer), amount_string(SignedCentsAmount::negative(purchase.amount), commodity))
+}
+
+fn write_transfer<W: Write>(out: &mut W, transfer: &Transfer, commodity: &str) -> std::io::Result<()> {
+    writeln!(out, "    {:<40} {:>12}", format!("{ASSETS}:{}", transfer.to), amount_string(SignedCentsAmount::positive(transfer.amount), commodity))?;
+    writeln!(out, "    {:<40} {:>12}", format!("{ASSETS}:{}", transfer.from), amount_string(SignedCentsAmount::negative(transfer.amount), commodity))
+}
+
+/// Writes `selected` as a ledger-cli journal, with amounts in `commodity`.
+pub fn write<'a, W: Write>(out: &mut W, selected: impl Iterator<Item = &'a Transaction>, tags: &Tags, commodity: &str) -> std::io::Result<()> {
+    for (index, transaction) in selected.enumerate() {
+        if index > 0 {
+            writeln!(out)?;
+        }
+        writeln!(out, "{} {}", transaction.date().naive().format("%Y-%m-%d"), transaction.desc())?;
+        if let Some(name) = transaction.recurring() {
+            writeln!(out, "    ; recurring: {name}")?;
+        }
+        match transaction {
+            Transaction::Purchase(purchase) => {
+                if let Some(foreign) = &purchase.foreign {
+                    writeln!(out, "    ; foreign: {} {}", foreign.amount.as_decimal(), foreign.currency)?;
+                }
+                if let Some(id) = &purchase.import_id {
+                    writeln!(out, "    ; import_id: {id}")?;
+                }
+                write_purchase(out, purchase, tags, commodity)?
+            },
+            Transaction::Transfer(transfer) => write_transfer(out, transfer, commodity)?,
+        }
+    }
+    Ok(())
+}
+
+
+
+struct Posting {
+    line: usize,
+    account: Vec<String>,
+    amount: Option<SignedCentsAmount>,
+    weight: Option<usize>,
+}
+
+struct Entry {
+    line: usize,
+    date: Date,
+    desc: String,
+    recurring: Option<String>,
+    import_id: Option<String>,
+    foreign: Option<ForeignAmount>,
+    postings: Vec<Posting>,
+}
+
+fn parse_amount(text: &str) -> Option<SignedCentsAmount> {
+    let negative = text.contains('-');
+    let text: String = text.chars().filter(|c| c.is_ascii_digit() || *c == '.' || *c == ',').collect();
+    // The last separator is the decimal mark, unless it is repeated, other ones group digits.
+    let digits = |text: &str| text.chars().filter(char::is_ascii_digit).collect::<String>();
+    let text = match text.rfind(['.', ',']) {
+        Some(index) if text.matches(&text[index..=index]).count() == 1 => format!("{}.{}", digits(&text[..index]), &text[index + 1..]),
+        _ => digits(&text),
+    };
+    let amount = CentsAmount::parse(&text)?;
+    Some(if negative { SignedCentsAmount::negative(amount) } else { SignedCentsAmount::positive(amount) })
+}
+
+/// Commodity of an amount, without the characters of its number.
+fn commodity_of(text: &str) -> String {
+    text.chars().filter(|c| !c.is_ascii_digit() && !matches!(c, '.' | ',' | '-' | '+') && !c.is_whitespace()).collect()
+}
+
+/// Amount written as `12.50 USD`.
+fn parse_foreign(line: usize, text: &str) -> Result<ForeignAmount, Error> {
+    let invalid = || Error::Syntax(line, format!("invalid foreign amount \"{text}\""));
+    let (amount, currency) = text.split_once(char::is_whitespace).ok_or_else(invalid)?;
+    let amount = CentsAmount::parse(amount).ok_or_else(invalid)?;
+    Ok(ForeignAmount{currency: currency.trim().to_string(), amount})
+}
+
+/// Value of `; name: value` in a comment.
+fn comment_tag<'a>(comment: &'a str, name: &str) -> Option<&'a str> {
+    let (key, value) = comment.split_once(':')?;
+    (key.trim() == name).then(|| value.trim())
+}
+
+fn parse_header(line: usize, text: &str) -> Result<Entry, Error> {
+    let (date, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    let date = ["%Y-%m-%d", "%Y/%m/%d"].iter()
+        .find_map(|format| NaiveDate::parse_from_str(date, format).ok())
+        .ok_or_else(|| Error::Syntax(line, format!("invalid date \"{date}\"")))?;
+
+    // Notes start with a `;` after whitespace, descriptions may hold other `;`.
+    let end = rest.char_indices().find(|(index, c)| *c == ';' && rest[..*index].ends_with([' ', '\t'])).map_or(rest.len(), |(index, _)| index);
+    let mut desc = rest[..end].trim();
+    desc = desc.strip_prefix(['*', '!']).unwrap_or(desc).trim_start();
+    if desc.starts_with('(') {
+        desc = desc.split_once(')').map_or(desc, |(_, rest)| rest.trim_start());
+    }
+
+    Ok(Entry{line, date: date.into(), desc: desc.to_string(), recurring: None, import_id: None, foreign: None, postings: Vec::new()})
+}
+
+fn parse_posting(line: usize, text: &str, commodity: &str) -> Result<Posting, Error> {
+    let (text, comment) = text.split_once(';').unwrap_or((text, ""));
+    let text = text.trim();
+    let (account, amount) = match text.find("  ").or_else(|| text.find('\t')) {
+        Some(index) => (text[..index].trim(), Some(text[index..].trim())),
+        None => (text, None),
+    };
+    let account = account.trim_start_matches(['*', '!']).trim_start();
+
+    // Amounts without a commodity are taken to be in the one of the ledger.
+    if let Some(other) = amount.map(commodity_of).filter(|x| !x.is_empty() && *x != commodity_of(commodity)) {
+        return Err(Error::Syntax(line, format!("amount in \"{other}\" instead of \"{}\"", commodity.trim())));
+    }
+    let amount = amount.map(|x| parse_amount(x).ok_or_else(|| Error::Syntax(line, format!("invalid amount \"{x}\"")))).transpose()?;
+    let weight = comment_tag(comment, "weight").and_then(|x| x.parse().ok()).filter(|x| *x > 0);
+
+    Ok(Posting{line, account: account.split(':').map(str::to_string).collect(), amount, weight})
+}
+
+/// Splits a journal into its transactions, skipping directives and comments.
+fn parse_entries(text: &str, commodity: &str) -> Result<Vec<Entry>, Error> {
+    let mut ret: Vec<Entry> = Vec::new();
+    let mut current: Option<Entry> = None;
+
+    for (index, raw) in text.lines().enumerate() {
+        let line = index + 1;
+        let indented = raw.starts_with([' ', '\t']);
+        let trimmed = raw.trim();
+
+        if !indented {
+            ret.extend(current.take());
+            if trimmed.starts_with(|c: char| c.is_ascii_digit()) {
+                current = Some(parse_header(line, trimmed)?);
+            }
+            continue;
+        }
+
+        let Some(entry) = current.as_mut() else {
+            continue;
+        };
+        if let Some(comment) = trimmed.strip_prefix(';') {
+            if let Some(name) = comment_tag(comment, "recurring") {
+                entry.recurring = Some(name.to_string());
+            }
+            if let Some(id) = comment_tag(comment, "import_id") {
+                entry.import_id = Some(id.to_string());
+            }
+            if let Some(text) = comment_tag(comment, "foreign") {
+                entry.foreign = Some(parse_foreign(line, text)?);
+            }
+        } else if !trimmed.is_empty() {
+            entry.postings.push(parse_posting(line, trimmed, commodity)?);
+        }
+    }
+    ret.extend(current);
+
+    Ok(ret)
+}
+
+/// Fills the elided amount of at most one posting so that the entry balances.
+fn balance(entry: &mut Entry) -> Result<(), Error> {
+    let total: i64 = entry.postings.iter().filter_map(|x| x.amount).map(|x| x.cents()).sum();
+    let mut elided = entry.postings.iter_mut().filter(|x| x.amount.is_none());
+    match (elided.next(), elided.next()) {
+        (Some(posting), None) => posting.amount = Some(SignedCentsAmount::new(-total)),
+        (None, _) if total == 0 => (),
+        (None, _) => return Err(Error::Syntax(entry.line, "transaction does not balance".to_string())),
+        (Some(_), Some(posting)) => return Err(Error::Syntax(posting.line, "several postings without amount".to_string())),
+    }
+    Ok(())
+}
+
+fn is_root(posting: &Posting, root: &str) -> bool {
+    posting.account.len() >= 2 && posting.account[0].eq_ignore_ascii_case(root)
+}
+
+fn to_transaction(mut entry: Entry, tags: &Tags, accounts: &Accounts) -> Result<Vec<Transaction>, Error> {
+    balance(&mut entry)?;
+    let check_account = |posting: &Posting| {
+        let account = &posting.account[1];
+        if accounts.0.contains_key(account) { Ok(account.clone()) } else { Err(Error::UnknownAccount(posting.line, account.clone())) }
+    };
+    let amount = |posting: &Posting| posting.amount.unwrap();
+
+    let (expenses, others): (Vec<&Posting>, Vec<&Posting>) = entry.postings.iter().partition(|x| is_root(x, EXPENSES));
+    let Some(funding) = others.iter().find(|x| amount(x).cents() < 0) else {
+        return Err(Error::Unsupported(entry.line));
+    };
+    if !is_root(funding, ASSETS) || others.len() > 2 || others.len() == 2 && !expenses.is_empty() {
+        return Err(Error::Unsupported(entry.line));
+    }
+    let source = check_account(funding)?;
+
+    if expenses.is_empty() {
+        let target = others.iter().find(|x| amount(x).cents() > 0).filter(|x| is_root(x, ASSETS)).ok_or(Error::Unsupported(entry.line))?;
+        let transfer = Transfer{date: entry.date, from: source, to: check_account(target)?, amount: amount(funding).abs(), desc: entry.desc};
+        return Ok(vec![Transaction::Transfer(transfer)]);
+    }
+
+    // One purchase per tag, each consumer posting weighted by its comment or its amount.
+    let mut per_tag: BTreeMap<TagRef, Vec<(AccountRef, CentsAmount, Option<usize>)>> = BTreeMap::new();
+    for posting in &expenses {
+        let tag = posting.account.last().unwrap();
+        if !tags.0.contains_key(tag) {
+            return Err(Error::UnknownTag(posting.line, tag.clone()));
+        }
+        if amount(posting).cents() < 0 {
+            return Err(Error::Unsupported(entry.line));
+        }
+        per_tag.entry(tag.clone()).or_default().push((check_account(posting)?, amount(posting).abs(), posting.weight));
+    }
+
+    let ret = per_tag.into_iter().map(|(tag, shares)| {
+        let total = shares.iter().fold(CentsAmount::new(0), |acc, (_, amount, _)| acc + *amount);
+        let divisor = shares.iter().fold(0, |acc, (_, amount, _)| gcd(acc, amount.cents()));
+        let mut consumers = BTreeMap::new();
+        for (account, amount, weight) in shares {
+            let weight = weight.unwrap_or_else(|| amount.cents().checked_div(divisor).map_or(1, |x| x as usize));
+            *consumers.entry(account).or_insert(0) += weight;
+        }
+        Transaction::Purchase(Purchase {
+            date: entry.date.clone(),
+            amount: total,
+            foreign: entry.foreign.clone(),
+            desc: entry.desc.clone(),
+            tag,
+            buyer: source.clone(),
+            consumers: Consumers(consumers),
+            recurring: entry.recurring.clone(),
+            import_id: entry.import_id.clone(),
+        })
+    }).collect();
+
+    Ok(ret)
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// Reads the transactions of a ledger-cli journal written with the same account layout as `write`,
+/// with amounts in `commodity` or without one.
+pub fn parse(text: &str, tags: &Tags, accounts: &Accounts, commodity: &str) -> Result<Vec<Transaction>, Error> {
+    let mut ret = Vec::new();
+    for entry in parse_entries(text, commodity)? {
+        ret.extend(to_transaction(entry, tags, accounts)?);
+    }
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags() -> Tags {
+        serde_yaml::from_str("
+food:
+  dur: 7
+  parent: null
+groceries:
+  dur: 3
+  parent: food
+").unwrap()
+    }
+
+    fn accounts() -> Accounts {
+        serde_yaml::from_str("
+alice:
+  color: e06c75
+bob:
+  color: 61afef
+").unwrap()
+    }
+
+    fn date(day: u32) -> Date {
+        NaiveDate::from_ymd_opt(2026, 3, day).unwrap().into()
+    }
+
+    #[test]
+    fn round_trip() {
+        let transactions = vec![
+            Transaction::Purchase(Purchase {
+                date: date(2),
+                amount: CentsAmount::new(10000),
+                foreign: Some(ForeignAmount{currency: "USD".to_string(), amount: CentsAmount::new(11000)}),
+                desc: "Market; weekly".to_string(),
+                tag: "groceries".to_string(),
+                buyer: "alice".to_string(),
+                consumers: Consumers([("alice".to_string(), 1), ("bob".to_string(), 2)].into_iter().collect()),
+                recurring: None,
+                import_id: Some("2026030201".to_string()),
+            }),
+            Transaction::Transfer(Transfer {
+                date: date(3),
+                from: "bob".to_string(),
+                to: "alice".to_string(),
+                amount: CentsAmount::new(3333),
+                desc: "Refund".to_string(),
+            }),
+        ];
+
+        let mut out = Vec::new();
+        write(&mut out, transactions.iter(), &tags(), "$").unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains("Expenses:bob:food:groceries"));
+        assert!(text.contains("-100.00 $"));
+        assert_eq!(parse(&text, &tags(), &accounts(), "$").unwrap(), transactions);
+        assert!(matches!(parse(&text, &tags(), &accounts(), "€"), Err(Error::Syntax(4, _))));
+    }
+
+    #[test]
+    fn elided_amount() {
+        let text = "
+2026/03/02 * (42) Market
+    Expenses:alice:food    10.00
+    Expenses:bob:food      20.00 €
+    Assets:bob
+
+2026-03-03 Refund
+    Assets:alice    5.00 €
+    Assets:bob
+";
+        let transactions = parse(text, &tags(), &accounts(), "€").unwrap();
+        assert_eq!(transactions, [
+            Transaction::Purchase(Purchase {
+                date: date(2),
+                amount: CentsAmount::new(3000),
+                foreign: None,
+                desc: "Market".to_string(),
+                tag: "food".to_string(),
+                buyer: "bob".to_string(),
+                consumers: Consumers([("alice".to_string(), 1), ("bob".to_string(), 2)].into_iter().collect()),
+                recurring: None,
+                import_id: None,
+            }),
+            Transaction::Transfer(Transfer {
+                date: date(3),
+                from: "bob".to_string(),
+                to: "alice".to_string(),
+                amount: CentsAmount::new(500),
+                desc: "Refund".to_string(),
+            }),
+        ]);
+
+        let text = "2026-03-02 Market\n    Expenses:alice:food\n    Assets:bob\n";
+        assert!(matches!(parse(text, &tags(), &accounts(), "€"), Err(Error::Syntax(3, _))));
+        let text = "2026-03-02 Market\n    Expenses:alice:food  10.00\n    Assets:bob  -9.00\n";
+        assert!(matches!(parse(text, &tags(), &accounts(), "€"), Err(Error::Syntax(1, _))));
+    }
+
+    #[test]
+    fn amounts() {
+        let cents = |text| parse_amount(text).map(|x| x.cents());
+        assert_eq!(cents("1.234,56"), Some(123456));
+        assert_eq!(cents("1,234.56"), Some(123456));
+        assert_eq!(cents("-1.234,5 €"), Some(-123450));
+        assert_eq!(cents("$-12"), Some(-1200));
+        assert_eq!(cents("1.234.567"), Some(123456700));
+        assert_eq!(cents("€"), None);
+    }
+}
@@ -0,0 +1,75 @@
+use std::env;
+use std::path::PathBuf;
+
+pub const LOCALCFG_FILE: &str = "localcfg.yaml";
+pub const TAGS_FILE: &str = "tags.yaml";
+pub const ACCOUNTS_FILE: &str = "accounts.yaml";
+pub const DATA_FILE: &str = "data.yaml";
+pub const RECURRING_FILE: &str = "recurring.yaml";
+pub const IMPORT_FILE: &str = "import.yaml";
+pub const RATES_FILE: &str = "rates.yaml";
+pub const CHART_FILE: &str = "chart.svg";
+
+/// Number of days, among those the data file was saved on, whose backup is kept.
+pub const BACKUP_DAYS: usize = 30;
+
+const DIR_VAR: &str = "EXPENSES_DIR";
+const LEDGER_VAR: &str = "EXPENSES_LEDGER";
+
+/// Directory holding the files of one ledger.
+/// Named ledgers are subdirectories of the base directory.
+#[derive(Debug, Clone)]
+pub struct LedgerDir {
+    base: PathBuf,
+    name: Option<String>,
+}
+
+impl LedgerDir {
+    /// Resolves the ledger directory from the command-line flags, then the
+    /// environment, then the XDG config directory.
+    pub fn resolve(dir: Option<String>, name: Option<String>) -> Self {
+        let base = dir
+            .or_else(|| env::var(DIR_VAR).ok())
+            .map(PathBuf::from)
+            .unwrap_or_else(Self::default_base);
+        let name = name.or_else(|| env::var(LEDGER_VAR).ok()).filter(|x| !x.is_empty());
+        Self{base, name}
+    }
+
+    fn default_base() -> PathBuf {
+        let config = env::var_os("XDG_CONFIG_HOME")
+            .filter(|x| !x.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+            .unwrap_or_else(|| PathBuf::from("."));
+        config.join("expenses")
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn path(&self) -> PathBuf {
+        match &self.name {
+            Some(name) => self.base.join(name),
+            None => self.base.clone(),
+        }
+    }
+
+    pub fn file(&self, file: &str) -> PathBuf {
+        self.path().join(file)
+    }
+
+    /// Names of the ledgers found in the base directory.
+    pub fn named_ledgers(&self) -> Vec<String> {
+        let mut ret: Vec<String> = std::fs::read_dir(&self.base)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|entry| entry.path().join(LOCALCFG_FILE).is_file())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect();
+        ret.sort();
+        ret
+    }
+}
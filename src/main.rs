@@ -4,17 +4,43 @@ mod tags;
 mod accounts;
 mod transaction;
 mod money;
+mod calc;
+mod formatting;
+mod currency;
 mod moneystate;
 mod color;
 mod datetime;
 mod yamlrw;
 mod completion;
+mod cli;
+mod ledger;
+mod setup;
+mod chart;
+mod recurring;
+mod budget;
+mod report;
+mod regex;
+mod csvrw;
+mod ofx;
+mod qif;
+mod import;
+mod export;
+mod journal;
 
 use std::error::Error;
 use crate::term::TermElement;
 
-fn app() -> Result<(), Box<dyn Error>> {
-    let mut app = interface::App::new();
+fn app(dir: ledger::LedgerDir) -> Result<(), Box<dyn Error>> {
+    if setup::needed(&dir) {
+        let mut wizard = setup::SetupWizard::new(dir.clone())?;
+        wizard.run()?;
+        if !wizard.done() {
+            return Ok(());
+        }
+        wizard.save()?;
+    }
+
+    let mut app = interface::App::new(dir)?;
     app.run()?;
     Ok(())
 }
@@ -36,9 +62,25 @@ fn setup_panic_hook() {
 }
 
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (dir, args) = match cli::global_options(&args) {
+        Ok(ret) => ret,
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(1);
+        },
+    };
+
+    if !args.is_empty() {
+        if let Err(err) = cli::run(&dir, args) {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
     setup_panic_hook();
-    match app() {
-        Err(err) => eprintln!("{err}"),
-        _ => (),
+    if let Err(err) = app(dir) {
+        eprintln!("{err}");
     }
 }
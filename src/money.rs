@@ -1,5 +1,12 @@
 use serde::{Serialize, Deserialize};
 
+/// Decimal mark and thousands separator used to write amounts.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Marks {
+    pub decimal: char,
+    pub thousands: Option<char>,
+}
+
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 pub struct CentsAmount {
     cents: u64,
@@ -14,6 +21,22 @@ impl CentsAmount {
         self.cents
     }
 
+    pub fn parse(text: &str) -> Option<Self> {
+        let (units, decimals) = match text.split_once(['.', ',']) {
+            Some((units, decimals)) => (units, decimals),
+            None => (text, ""),
+        };
+        if units.is_empty() && decimals.is_empty() || decimals.len() > 2 {
+            return None;
+        }
+        if !units.chars().chain(decimals.chars()).all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let units: u64 = if units.is_empty() { 0 } else { units.parse().ok()? };
+        let decimals: u64 = format!("{:0<2}", decimals).parse().ok()?;
+        Some(Self::new(units.checked_mul(100)?.checked_add(decimals)?))
+    }
+
     pub fn digits(&self) -> Vec<usize> {
         let mut ret = Vec::new();
         let mut value = self.cents;
@@ -26,7 +49,12 @@ impl CentsAmount {
         ret
     }
 
-    pub fn as_string_exact(&self, separator: bool) -> String {
+    /// Plain `1234.56`, for files read by other programs.
+    pub fn as_decimal(&self) -> String {
+        format!("{}.{:02}", self.cents / 100, self.cents % 100)
+    }
+
+    pub fn as_string_exact(&self, marks: Marks) -> String {
         let mut digits = self.digits();
         let scale = 2;
         while digits.len() <= scale {
@@ -42,9 +70,9 @@ impl CentsAmount {
                 break;
             }
             if index == scale {
-                ret.push('.');
-            } else if index % 3 == 2 && separator {
-                ret.push(',');
+                ret.push(marks.decimal);
+            } else if let Some(c) = marks.thousands.filter(|_| index % 3 == 2) {
+                ret.push(c);
             }
             index -= 1;
         }
@@ -52,11 +80,12 @@ impl CentsAmount {
         ret.into_iter().collect()
     }
 
-    pub fn as_string_precision(&self, nb_digits: usize, separator: bool) -> String {
+    #[allow(clippy::identity_op)]
+    pub fn as_string_precision(&self, nb_digits: usize, marks: Marks) -> String {
         assert!(nb_digits > 0);
         let mut digits = self.digits();
         if digits.len() <= nb_digits {
-            self.as_string_exact(separator)
+            self.as_string_exact(marks)
         } else {
             let smallest_index = digits.len() - nb_digits;
             let (indicator, scale) = if smallest_index <= 2+0 {
@@ -84,9 +113,9 @@ impl CentsAmount {
                     break;
                 }
                 if index == scale {
-                    ret.push('.');
-                } else if index % 3 == 2 && separator {
-                    ret.push(',');
+                    ret.push(marks.decimal);
+                } else if let Some(c) = marks.thousands.filter(|_| index % 3 == 2) {
+                    ret.push(c);
                 }
                 index -= 1;
             }
@@ -99,32 +128,28 @@ impl CentsAmount {
         }
     }
 
-    pub fn as_string_width(&self, width: usize, separator: bool) -> String {
+    pub fn as_string_width(&self, width: usize, marks: Marks) -> String {
         let mut nb_digits = 1;
-        if self.as_string_exact(separator).len() <= width {
-            self.as_string_exact(separator)
+        if self.as_string_exact(marks).chars().count() <= width {
+            self.as_string_exact(marks)
         } else {
-            while self.as_string_precision(nb_digits+1, separator).len() <= width {
+            while self.as_string_precision(nb_digits+1, marks).chars().count() <= width {
                 nb_digits += 1;
             }
-            self.as_string_precision(nb_digits, separator)
+            self.as_string_precision(nb_digits, marks)
         }
     }
 
-    pub fn as_string_width_padded(&self, width: usize, separator: bool) -> String {
-        format!("{: >width$}", self.as_string_width(width, separator), width = width)
-    }
-
     pub fn subdiv(&self, weights: Vec<usize>) -> Vec<Self> {
         assert!(!weights.is_empty());
         let wsum: u64 = weights.iter().sum::<usize>() as u64;
         let mut ret: Vec<u64> = weights.iter().map(|w| self.cents * *w as u64 / wsum).collect();
         let ret_sum: u64 = ret.iter().sum();
         let rem = (self.cents - ret_sum) as usize;
-        for k in 0..rem {
-            ret[k] += 1;
+        for cents in ret.iter_mut().take(rem) {
+            *cents += 1;
         }
-        ret.into_iter().map(|c| Self::new(c)).collect()
+        ret.into_iter().map(Self::new).collect()
     }
 }
 
@@ -200,39 +225,31 @@ impl SignedCentsAmount {
         CentsAmount{cents: self.cents.abs_diff(0)}
     }
 
-    pub fn as_string_exact(&self, separator: bool) -> String {
-        use std::cmp::Ordering::*;
-
-        match self.cents.cmp(&0) {
-            Less => format!("-{}", self.abs().as_string_exact(separator)),
-            Equal => format!("{}", self.abs().as_string_exact(separator)),
-            Greater => format!("+{}", self.abs().as_string_exact(separator)),
-        }
+    /// Plain `-1234.56`, for files read by other programs.
+    pub fn as_decimal(&self) -> String {
+        let sign = if self.cents < 0 { "-" } else { "" };
+        format!("{sign}{}", self.abs().as_decimal())
     }
 
-    pub fn as_string_precision(&self, nb_digits: usize, separator: bool) -> String {
+    pub fn as_string_exact(&self, marks: Marks) -> String {
         use std::cmp::Ordering::*;
 
         match self.cents.cmp(&0) {
-            Less => format!("-{}", self.abs().as_string_precision(nb_digits, separator)),
-            Equal => format!("{}", self.abs().as_string_precision(nb_digits, separator)),
-            Greater => format!("+{}", self.abs().as_string_precision(nb_digits, separator)),
+            Less => format!("-{}", self.abs().as_string_exact(marks)),
+            Equal => self.abs().as_string_exact(marks),
+            Greater => format!("+{}", self.abs().as_string_exact(marks)),
         }
     }
 
-    pub fn as_string_width(&self, width: usize, separator: bool) -> String {
+    pub fn as_string_width(&self, width: usize, marks: Marks) -> String {
         use std::cmp::Ordering::*;
 
         match self.cents.cmp(&0) {
-            Less => format!("-{}", self.abs().as_string_width(width-1, separator)),
-            Equal => format!("{}", self.abs().as_string_width(width-1, separator)),
-            Greater => format!("+{}", self.abs().as_string_width(width-1, separator)),
+            Less => format!("-{}", self.abs().as_string_width(width-1, marks)),
+            Equal => self.abs().as_string_width(width-1, marks),
+            Greater => format!("+{}", self.abs().as_string_width(width-1, marks)),
         }
     }
-
-    pub fn as_string_width_padded(&self, width: usize, separator: bool) -> String {
-        format!("{: >width$}", self.as_string_width(width, separator), width = width)
-    }
 }
 
 impl std::ops::Add for SignedCentsAmount {
@@ -11,26 +11,137 @@ pub struct Balance {
     external: SignedCentsAmount,
 }
 
+impl Balance {
+    pub fn new() -> Self {
+        Self{external: SignedCentsAmount::new(0)}
+    }
+
+    pub fn external(&self) -> SignedCentsAmount {
+        self.external
+    }
+
+    pub fn add(&mut self, delta: SignedCentsAmount) {
+        self.external = self.external + delta;
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Balances(pub BTreeMap<AccountRef, Balance>);
 
+impl Balances {
+    pub fn new() -> Self {
+        Self(BTreeMap::new())
+    }
 
+    pub fn add(&mut self, transaction: &Transaction) {
+        for account in transaction.accounts() {
+            self.0.entry(account.clone()).or_insert_with(Balance::new).add(transaction.external_delta(&account));
+        }
+    }
 
-#[derive(Debug, Copy, Clone)]
-pub struct Flow(pub CentsAmount);
+    /// Repayments bringing every external balance to zero, as few as possible.
+    ///
+    /// Accounts whose balances sum to zero can settle among themselves with one repayment
+    /// less than their number, so the fewest repayments come from splitting the accounts
+    /// into as many such groups as possible. The search is exponential in the number of
+    /// accounts, so beyond `EXACT_SETTLEMENT_MAX` of them they are settled as a single group.
+    pub fn settlement(&self) -> Vec<Repayment> {
+        let owed: Vec<(AccountRef, i64)> = self.0.iter()
+            .map(|(account, balance)| (account.clone(), balance.external.cents()))
+            .filter(|(_, cents)| *cents != 0)
+            .collect();
+        let groups = if owed.len() <= EXACT_SETTLEMENT_MAX { zero_sum_groups(owed) } else { vec![owed] };
+        groups.iter().flat_map(|group| settle_group(group)).collect()
+    }
+}
 
-impl Flow {
-    pub fn approx(amount: CentsAmount, without: &FlowState, with: &FlowState) -> Self {
-        if amount.cents() == 0 {
-            Self(CentsAmount::new(0))
-        } else {
-            Self(CentsAmount::new(amount.cents() * with.flow().0.cents() / (with.amount.cents() - without.amount.cents())))
+const EXACT_SETTLEMENT_MAX: usize = 16;
+
+/// Partition of `owed` into the largest number of groups summing to zero, the last group
+/// holding the remainder if the total is not zero.
+fn zero_sum_groups(owed: Vec<(AccountRef, i64)>) -> Vec<Vec<(AccountRef, i64)>> {
+    let n = owed.len();
+    let full = (1usize << n) - 1;
+
+    let mut sums = vec![0i64; full + 1];
+    for mask in 1..=full {
+        sums[mask] = sums[mask & (mask - 1)] + owed[mask.trailing_zeros() as usize].1;
+    }
+
+    // Largest number of zero sum prefixes over the orders of the accounts of each subset.
+    let mut counts = vec![0usize; full + 1];
+    for mask in 1..=full {
+        let best = (0..n).filter(|i| mask >> i & 1 == 1).map(|i| counts[mask ^ 1 << i]).max().unwrap_or(0);
+        counts[mask] = best + usize::from(sums[mask] == 0);
+    }
+
+    let mut order = Vec::new();
+    let mut mask = full;
+    while mask != 0 {
+        let zero = usize::from(sums[mask] == 0);
+        let last = (0..n).find(|i| mask >> i & 1 == 1 && counts[mask ^ 1 << i] + zero == counts[mask]).unwrap();
+        order.push(last);
+        mask ^= 1 << last;
+    }
+
+    let mut ret = Vec::new();
+    let mut group = Vec::new();
+    let mut sum = 0;
+    for index in order.into_iter().rev() {
+        sum += owed[index].1;
+        group.push(owed[index].clone());
+        if sum == 0 {
+            ret.push(std::mem::take(&mut group));
         }
     }
+    if !group.is_empty() {
+        ret.push(group);
+    }
+    ret
+}
+
+/// Repayments within `group`, from the largest debt to the largest credit, which takes
+/// one repayment less than the number of accounts when the group sums to zero.
+fn settle_group(group: &[(AccountRef, i64)]) -> Vec<Repayment> {
+    let mut creditors: Vec<(AccountRef, u64)> = group.iter().filter(|(_, cents)| *cents > 0).map(|(a, cents)| (a.clone(), cents.unsigned_abs())).collect();
+    let mut debtors: Vec<(AccountRef, u64)> = group.iter().filter(|(_, cents)| *cents < 0).map(|(a, cents)| (a.clone(), cents.unsigned_abs())).collect();
+
+    let mut ret = Vec::new();
+
+    loop {
+        creditors.sort_by_key(|(_, cents)| *cents);
+        debtors.sort_by_key(|(_, cents)| *cents);
+
+        let (Some(creditor), Some(debtor)) = (creditors.last_mut(), debtors.last_mut()) else {
+            break;
+        };
+
+        let cents = u64::min(creditor.1, debtor.1);
+        ret.push(Repayment{from: debtor.0.clone(), to: creditor.0.clone(), amount: CentsAmount::new(cents)});
+        creditor.1 -= cents;
+        debtor.1 -= cents;
+
+        creditors.retain(|(_, cents)| *cents > 0);
+        debtors.retain(|(_, cents)| *cents > 0);
+    }
+
+    ret
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repayment {
+    pub from: AccountRef,
+    pub to: AccountRef,
+    pub amount: CentsAmount,
 }
 
 
 
+#[derive(Debug, Copy, Clone)]
+pub struct Flow(pub CentsAmount);
+
+
+
 #[derive(Debug, Copy, Clone)]
 pub struct SignedFlow(pub SignedCentsAmount);
 
@@ -84,12 +195,6 @@ impl FlowState {
         Flow(absorbed)
     }
 
-    pub fn next(&self) -> Self {
-        let mut ret = self.clone();
-        ret.step();
-        ret
-    }
-
     pub fn add(&mut self, amount: CentsAmount, dur: usize) {
         if amount.cents() == 0 {
             return;
@@ -102,6 +207,7 @@ impl FlowState {
 
 
 
+#[derive(Debug, Clone)]
 pub struct FlowStates(pub BTreeMap<TagRef, FlowState>);
 
 impl FlowStates {
@@ -124,6 +230,7 @@ impl FlowStates {
 
 
 
+#[derive(Debug, Clone)]
 pub struct FlowStatesSnapshot {
     date: Date,
     state: FlowStates,
@@ -164,3 +271,64 @@ impl FlowStatesSnapshot {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn balances(cents: &[(&str, i64)]) -> Balances {
+        Balances(cents.iter().map(|(account, cents)| (account.to_string(), Balance{external: SignedCentsAmount::new(*cents)})).collect())
+    }
+
+    fn repayment(from: &str, to: &str, cents: u64) -> Repayment {
+        Repayment{from: from.to_string(), to: to.to_string(), amount: CentsAmount::new(cents)}
+    }
+
+    /// Balances once every repayment is made.
+    fn settled(mut balances: Balances, repayments: &[Repayment]) -> Balances {
+        for repayment in repayments {
+            balances.0.get_mut(&repayment.from).unwrap().add(SignedCentsAmount::positive(repayment.amount));
+            balances.0.get_mut(&repayment.to).unwrap().add(SignedCentsAmount::negative(repayment.amount));
+        }
+        balances
+    }
+
+    fn all_zero(balances: &Balances) -> bool {
+        balances.0.values().all(|balance| balance.external().cents() == 0)
+    }
+
+    #[test]
+    fn settled_already() {
+        assert_eq!(balances(&[]).settlement(), vec![]);
+        assert_eq!(balances(&[("alice", 0), ("bob", 0)]).settlement(), vec![]);
+    }
+
+    #[test]
+    fn one_creditor() {
+        let balances = balances(&[("alice", 3000), ("bob", -1000), ("carol", -2000)]);
+        let repayments = balances.settlement();
+        assert_eq!(repayments, vec![repayment("carol", "alice", 2000), repayment("bob", "alice", 1000)]);
+        assert!(all_zero(&settled(balances, &repayments)));
+    }
+
+    #[test]
+    fn fewest_repayments() {
+        // Matching the largest debt with the largest credit takes 4 repayments here,
+        // while b and e settle between themselves and a, c and d in 2 more.
+        let balances = balances(&[("a", -900), ("b", -800), ("c", 200), ("d", 700), ("e", 800)]);
+        let repayments = balances.settlement();
+        assert_eq!(repayments.len(), 3);
+        assert!(repayments.contains(&repayment("b", "e", 800)));
+        assert!(all_zero(&settled(balances, &repayments)));
+    }
+
+    #[test]
+    fn many_accounts() {
+        let cents: Vec<(String, i64)> = (0..20).map(|index| (format!("account{index:02}"), if index % 2 == 0 { 100 * index } else { -100 * (index - 1) })).collect();
+        let cents: Vec<(&str, i64)> = cents.iter().map(|(account, cents)| (account.as_str(), *cents)).collect();
+        let balances = balances(&cents);
+        let repayments = balances.settlement();
+        assert!(repayments.len() < 20);
+        assert!(all_zero(&settled(balances, &repayments)));
+    }
+}
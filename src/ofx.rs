@@ -0,0 +1,149 @@
+use std::collections::BTreeMap;
+use chrono::NaiveDate;
+
+use crate::money::*;
+use crate::import::Entry;
+
+/// Tags of an OFX document with the text following them, closing tags included.
+/// Works for both SGML (1.x) documents, whose leaf elements are not closed, and XML (2.x) ones.
+fn elements(text: &str) -> Vec<(String, String)> {
+    let mut ret = Vec::new();
+    let mut rest = text;
+    while let Some(begin) = rest.find('<') {
+        let Some(end) = rest[begin..].find('>') else {
+            break;
+        };
+        let tag = rest[begin+1..begin+end].trim().to_uppercase();
+        rest = &rest[begin+end+1..];
+        let value = rest.find('<').map_or(rest, |next| &rest[..next]);
+        ret.push((tag, unescape(value.trim())));
+    }
+    ret
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&apos;", "'").replace("&nbsp;", " ").replace("&amp;", "&")
+}
+
+fn amount(text: &str) -> Option<SignedCentsAmount> {
+    let (negative, text) = match text.strip_prefix('-') {
+        Some(text) => (true, text),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let amount = CentsAmount::parse(text)?;
+    Some(if negative { SignedCentsAmount::negative(amount) } else { SignedCentsAmount::positive(amount) })
+}
+
+/// `None` for invalid transactions and income.
+fn entry(fields: &BTreeMap<String, String>) -> Option<Entry> {
+    // Dates are `YYYYMMDD` optionally followed by the time and timezone.
+    let date = NaiveDate::parse_from_str(fields.get("DTPOSTED")?.get(..8)?, "%Y%m%d").ok()?;
+    let amount = amount(fields.get("TRNAMT")?)?;
+    let desc = fields.get("NAME").or_else(|| fields.get("MEMO")).map_or(String::new(), |x| x.split_whitespace().collect::<Vec<_>>().join(" "));
+    let id = fields.get("FITID").filter(|x| !x.is_empty()).cloned();
+
+    (amount.cents() < 0).then(|| Entry{date: date.into(), amount: amount.abs(), desc, id})
+}
+
+/// Spending entries of the `STMTTRN` elements of an OFX document, and the number of ignored ones.
+/// Descriptions are taken from `NAME`, or `MEMO` when it is missing.
+pub fn parse(text: &str) -> (Vec<Entry>, usize) {
+    let mut entries = Vec::new();
+    let mut ignored = 0;
+    let mut current: Option<BTreeMap<String, String>> = None;
+
+    for (tag, value) in elements(text) {
+        match tag.as_str() {
+            "STMTTRN" => current = Some(BTreeMap::new()),
+            "/STMTTRN" => match current.take().as_ref().and_then(entry) {
+                Some(entry) => entries.push(entry),
+                None => ignored += 1,
+            },
+            _ => if let Some(fields) = current.as_mut() {
+                if !tag.starts_with('/') && !value.is_empty() {
+                    fields.insert(tag, value);
+                }
+            },
+        }
+    }
+
+    (entries, ignored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(entries: &[Entry]) -> Vec<(String, u64, &str, Option<&str>)> {
+        entries.iter().map(|x| (x.date.naive().to_string(), x.amount.cents(), x.desc.as_str(), x.id.as_deref())).collect()
+    }
+
+    #[test]
+    fn sgml() {
+        let text = "OFXHEADER:100
+DATA:OFXSGML
+VERSION:102
+
+<OFX>
+<BANKMSGSRSV1><STMTTRNRS><STMTRS>
+<BANKTRANLIST>
+<STMTTRN>
+<TRNTYPE>DEBIT
+<DTPOSTED>20260302120000[-5:EST]
+<TRNAMT>-12.50
+<FITID>A1
+<NAME>Bread  &amp; Butter
+</STMTTRN>
+<STMTTRN>
+<TRNTYPE>CREDIT
+<DTPOSTED>20260303
+<TRNAMT>1000.00
+<FITID>A2
+<NAME>Salary
+</STMTTRN>
+<STMTTRN>
+<TRNTYPE>DEBIT
+<DTPOSTED>20260304
+<TRNAMT>-3
+<FITID>
+<MEMO>Parking
+</STMTTRN>
+</BANKTRANLIST>
+</STMTRS></STMTTRNRS></BANKMSGSRSV1>
+</OFX>
+";
+        let (entries, ignored) = parse(text);
+        assert_eq!(summary(&entries), [
+            ("2026-03-02".to_string(), 1250, "Bread & Butter", Some("A1")),
+            ("2026-03-04".to_string(), 300, "Parking", None),
+        ]);
+        assert_eq!(ignored, 1);
+    }
+
+    #[test]
+    fn xml() {
+        let text = r#"<?xml version="1.0" encoding="UTF-8"?>
+<?OFX OFXHEADER="200" VERSION="220"?>
+<OFX>
+  <BANKMSGSRSV1><STMTTRNRS><STMTRS><BANKTRANLIST>
+    <STMTTRN>
+      <TRNTYPE>DEBIT</TRNTYPE>
+      <DTPOSTED>20260305</DTPOSTED>
+      <TRNAMT>-42.00</TRNAMT>
+      <FITID>B1</FITID>
+      <NAME>Hardware store</NAME>
+      <MEMO>Card 1234</MEMO>
+    </STMTTRN>
+    <STMTTRN>
+      <TRNTYPE>DEBIT</TRNTYPE>
+      <DTPOSTED>2026-03-06</DTPOSTED>
+      <TRNAMT>-1.00</TRNAMT>
+    </STMTTRN>
+  </BANKTRANLIST></STMTRS></STMTTRNRS></BANKMSGSRSV1>
+</OFX>
+"#;
+        let (entries, ignored) = parse(text);
+        assert_eq!(summary(&entries), [("2026-03-05".to_string(), 4200, "Hardware store", Some("B1"))]);
+        assert_eq!(ignored, 1);
+    }
+}
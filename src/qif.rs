@@ -0,0 +1,136 @@
+use chrono::NaiveDate;
+
+use crate::money::*;
+use crate::import::Entry;
+
+/// Amounts may use either `.` or `,` as decimal separator, the other one grouping thousands.
+fn amount(text: &str) -> Option<SignedCentsAmount> {
+    let negative = text.trim_start().starts_with('-');
+    let mut text: String = text.chars().filter(|c| c.is_ascii_digit() || *c == '.' || *c == ',').collect();
+    let decimal = text.rfind(['.', ',']).filter(|index| text.len() - index <= 3);
+    if let Some(index) = decimal {
+        text.replace_range(index..index+1, "|");
+    }
+    text.retain(|c| c != '.' && c != ',');
+    let amount = CentsAmount::parse(&text.replace('|', "."))?;
+    Some(if negative { SignedCentsAmount::negative(amount) } else { SignedCentsAmount::positive(amount) })
+}
+
+/// Quicken writes years after 1999 as `'YY` and pads days with spaces, as in `1/ 5'24`.
+fn date(text: &str, format: &str) -> Option<NaiveDate> {
+    let text: String = text.chars().filter(|c| !c.is_whitespace()).map(|c| if c == '\'' { '/' } else { c }).collect();
+    [format.replace("%Y", "%y"), format.to_string()].iter().find_map(|format| NaiveDate::parse_from_str(&text, format).ok())
+}
+
+#[derive(Default)]
+struct Record {
+    date: Option<String>,
+    amount: Option<String>,
+    payee: Option<String>,
+    memo: Option<String>,
+}
+
+impl Record {
+    fn is_empty(&self) -> bool {
+        self.date.is_none() && self.amount.is_none()
+    }
+
+    /// `None` for invalid records and income.
+    fn entry(&self, date_format: &str) -> Option<Entry> {
+        let date = date(self.date.as_ref()?, date_format)?;
+        let amount = amount(self.amount.as_ref()?)?;
+        let desc = self.payee.as_ref().or(self.memo.as_ref()).map_or(String::new(), |x| x.split_whitespace().collect::<Vec<_>>().join(" "));
+
+        (amount.cents() < 0).then(|| Entry{date: date.into(), amount: amount.abs(), desc, id: None})
+    }
+}
+
+/// Whether the records following the `!` header `header` are transactions,
+/// rather than accounts, categories or other lists.
+fn lists_transactions(header: &str) -> bool {
+    match header.strip_prefix("Type:") {
+        Some(kind) => !["Cat", "Class", "Memorized", "Prices", "Security"].iter().any(|x| kind.trim().eq_ignore_ascii_case(x)),
+        None => false,
+    }
+}
+
+/// Spending entries of a QIF file, and the number of ignored records.
+/// Records without date nor amount and those of lists other than transactions,
+/// such as account definitions, are skipped silently.
+pub fn parse(text: &str, date_format: &str) -> (Vec<Entry>, usize) {
+    let mut entries = Vec::new();
+    let mut ignored = 0;
+    let mut record = Record::default();
+    let mut transactions = true;
+
+    for line in text.trim_start_matches('\u{feff}').lines() {
+        let Some(code) = line.chars().next() else {
+            continue;
+        };
+        let value = Some(line[code.len_utf8()..].trim().to_string());
+        match code {
+            '!' => transactions = lists_transactions(line[1..].trim()),
+            _ if !transactions => (),
+            'D' => record.date = value,
+            'T' | 'U' if record.amount.is_none() => record.amount = value,
+            'P' => record.payee = value,
+            'M' => record.memo = value,
+            '^' => {
+                let done = std::mem::take(&mut record);
+                if done.is_empty() {
+                    continue;
+                }
+                match done.entry(date_format) {
+                    Some(entry) => entries.push(entry),
+                    None => ignored += 1,
+                }
+            },
+            _ => (),
+        }
+    }
+
+    (entries, ignored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records() {
+        let text = "\u{feff}!Account
+NChecking
+TBank
+^
+!Type:Bank
+D3/ 2'26
+T-1,234.56
+PLandlord
+^
+D03/04/2026
+U-7,50
+MCoffee
+^
+D3/ 5'26
+T2,000.00
+PSalary
+^
+D3/32'26
+T-1.00
+^
+";
+        let (entries, ignored) = parse(text, "%m/%d/%Y");
+        let summary: Vec<_> = entries.iter().map(|x| (x.date.naive().to_string(), x.amount.cents(), x.desc.as_str(), x.id.is_none())).collect();
+        assert_eq!(summary, [
+            ("2026-03-02".to_string(), 123456, "Landlord", true),
+            ("2026-03-04".to_string(), 750, "Coffee", true),
+        ]);
+        assert_eq!(ignored, 2);
+    }
+
+    #[test]
+    fn day_first() {
+        let (entries, _) = parse("D04/03'26\nT-5.00\n^\n", "%d/%m/%Y");
+        assert_eq!(entries[0].date.naive().to_string(), "2026-03-04");
+    }
+}
@@ -0,0 +1,288 @@
+use std::error;
+use std::fmt;
+use std::collections::{BTreeMap, BTreeSet};
+use chrono::{Datelike, NaiveDate};
+use serde::{Serialize, Deserialize};
+
+use crate::tags::{Tags, TagRef};
+use crate::accounts::{Accounts, AccountRef};
+use crate::transaction::{Transactions, Transaction, Purchase, Consumers};
+use crate::money::CentsAmount;
+use crate::currency::{self, Rates, ForeignAmount};
+use crate::datetime::Date;
+use crate::yamlrw::YamlRW;
+use crate::ledger::*;
+
+#[derive(Debug)]
+pub enum Error {
+    UnknownTag(String, TagRef),
+    UnknownAccount(String, AccountRef),
+    InvalidSchedule(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::UnknownTag(ref name, ref tag) =>
+                write!(f, "Recurring transaction \"{name}\" uses unknown tag \"{tag}\""),
+            Error::UnknownAccount(ref name, ref account) =>
+                write!(f, "Recurring transaction \"{name}\" uses unknown account \"{account}\""),
+            Error::InvalidSchedule(ref name) =>
+                write!(f, "Recurring transaction \"{name}\" has an invalid schedule"),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Schedule {
+    /// Every week on the given weekday, 1 for Monday to 7 for Sunday.
+    Weekly(u32),
+    /// Every month on the given day, moved to the last day of shorter months.
+    Monthly(u32),
+    Yearly{month: u32, day: u32},
+}
+
+fn last_day_of_month(date: NaiveDate) -> u32 {
+    (28..=31).rev().find(|day| date.with_day(*day).is_some()).unwrap()
+}
+
+impl Schedule {
+    fn valid(&self) -> bool {
+        match *self {
+            Schedule::Weekly(weekday) => (1..=7).contains(&weekday),
+            Schedule::Monthly(day) => (1..=31).contains(&day),
+            Schedule::Yearly{month, day} => (1..=12).contains(&month) && (1..=31).contains(&day),
+        }
+    }
+
+    fn matches(&self, date: &Date) -> bool {
+        let date = date.naive();
+        match *self {
+            Schedule::Weekly(weekday) => date.weekday().number_from_monday() == weekday,
+            Schedule::Monthly(day) => date.day() == u32::min(day, last_day_of_month(date)),
+            Schedule::Yearly{month, day} => date.month() == month && date.day() == u32::min(day, last_day_of_month(date)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecurringData {
+    schedule: Schedule,
+    start: Date,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    end: Option<Date>,
+    /// Last date occurrences were generated up to, so that removed ones are not generated again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last: Option<Date>,
+    /// Occurrences not to generate.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    skip: BTreeSet<Date>,
+    amount: CentsAmount,
+    /// Currency of `amount` when it is not the one of the ledger.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    currency: Option<String>,
+    desc: String,
+    tag: TagRef,
+    buyer: AccountRef,
+    consumers: Consumers,
+}
+
+impl RecurringData {
+    /// Dates of the occurrences not generated yet, up to `today` included.
+    fn due(&self, today: &Date) -> Vec<Date> {
+        let mut ret = Vec::new();
+        let mut date = match &self.last {
+            Some(last) => Date::max(last.succ(), self.start.clone()),
+            None => self.start.clone(),
+        };
+        let end = match &self.end {
+            Some(end) => Date::min(end.clone(), today.clone()),
+            None => today.clone(),
+        };
+
+        while date <= end {
+            if self.schedule.matches(&date) && !self.skip.contains(&date) {
+                ret.push(date.clone());
+            }
+            date.incr();
+        }
+
+        ret
+    }
+
+    fn purchase(&self, name: &str, date: Date) -> Purchase {
+        Purchase {
+            date,
+            amount: self.amount,
+            foreign: self.currency.clone().map(|currency| ForeignAmount{currency, amount: self.amount}),
+            desc: self.desc.clone(),
+            tag: self.tag.clone(),
+            buyer: self.buyer.clone(),
+            consumers: self.consumers.clone(),
+            recurring: Some(name.to_string()),
+            import_id: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recurring(pub BTreeMap<String, RecurringData>);
+
+impl YamlRW for Recurring {}
+
+impl Recurring {
+    pub fn check(&self, tags: &Tags, accounts: &Accounts) -> Result<(), Error> {
+        for (name, data) in &self.0 {
+            if !data.schedule.valid() {
+                return Err(Error::InvalidSchedule(name.clone()));
+            }
+            if !tags.0.contains_key(&data.tag) {
+                return Err(Error::UnknownTag(name.clone(), data.tag.clone()));
+            }
+            if let Some(account) = std::iter::once(&data.buyer).chain(data.consumers.0.keys()).find(|x| !accounts.0.contains_key(*x)) {
+                return Err(Error::UnknownAccount(name.clone(), account.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Currencies used by recurring transactions that have no rate.
+    pub fn missing_rates(&self, rates: &Rates) -> BTreeSet<String> {
+        self.0.values().filter_map(|data| data.currency.clone()).filter(|currency| !rates.contains(currency)).collect()
+    }
+
+    /// Adds the occurrences due up to `today` to `transactions` and returns how many were added.
+    /// Occurrences already in `transactions` are not added again, and those in a currency
+    /// without a rate are left for when it is given.
+    pub fn materialize(&mut self, transactions: &mut Transactions, rates: &Rates, today: &Date) -> Result<usize, currency::Error> {
+        let mut ret = 0;
+        for (name, data) in self.0.iter_mut() {
+            if data.currency.as_ref().is_some_and(|currency| !rates.contains(currency)) {
+                continue;
+            }
+            for date in data.due(today) {
+                if transactions.vec().iter().any(|tr| matches!(tr, Transaction::Purchase(purchase) if purchase.date == date && purchase.recurring.as_ref() == Some(name))) {
+                    continue;
+                }
+                let mut purchase = data.purchase(name, date);
+                rates.apply(&mut purchase)?;
+                transactions.add(Transaction::Purchase(purchase));
+                ret += 1;
+            }
+            if data.last.as_ref().is_none_or(|last| last < today) {
+                data.last = Some(today.clone());
+            }
+        }
+        Ok(ret)
+    }
+}
+
+/// Generates the due recurring transactions of the ledger, if it defines any,
+/// and saves the data and recurring files when something was added.
+/// Returns how many were added and the currencies of those left for later.
+pub fn materialize(dir: &LedgerDir, tags: &Tags, accounts: &Accounts, rates: &Rates, transactions: &mut Transactions) -> Result<(usize, BTreeSet<String>), Box<dyn error::Error>> {
+    let path = dir.file(RECURRING_FILE);
+    if !path.exists() {
+        return Ok((0, BTreeSet::new()));
+    }
+
+    let mut recurring = Recurring::read_yaml(&path)?;
+    recurring.check(tags, accounts)?;
+
+    let lasts: Vec<Option<Date>> = recurring.0.values().map(|data| data.last.clone()).collect();
+    let count = recurring.materialize(transactions, rates, &Date::today())?;
+    if count > 0 {
+        // Should the second write fail, the next run finds the occurrences in the data file.
+        transactions.write_yaml_with_backup(dir.file(DATA_FILE), BACKUP_DAYS)?;
+    }
+    if recurring.0.values().map(|data| &data.last).ne(lasts.iter()) {
+        recurring.write_yaml(&path)?;
+    }
+
+    Ok((count, recurring.missing_rates(rates)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(day: u32) -> Date {
+        NaiveDate::from_ymd_opt(2026, 3, day).unwrap().into()
+    }
+
+    fn recurring() -> Recurring {
+        serde_yaml::from_str("
+rent:
+  schedule: !Monthly 1
+  start: 2026-01-01
+  amount:
+    cents: 80000
+  desc: Rent
+  tag: rent
+  buyer: alice
+  consumers:
+    alice: 1
+    bob: 1
+netflix:
+  schedule: !Weekly 1
+  start: 2026-03-01
+  currency: USD
+  amount:
+    cents: 500
+  desc: Streaming
+  tag: leisure
+  buyer: bob
+  consumers:
+    bob: 1
+").unwrap()
+    }
+
+    fn rates() -> Rates {
+        Rates([("USD".to_string(), [(day(1), 0.9)].into_iter().collect())].into_iter().collect())
+    }
+
+    fn dates(transactions: &Transactions, name: &str) -> Vec<Date> {
+        transactions.vec().iter()
+            .filter_map(|tr| match tr {
+                Transaction::Purchase(purchase) if purchase.recurring.as_deref() == Some(name) => Some(purchase.date.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn repeatable() {
+        let mut transactions = Transactions::new();
+        let mut first = recurring();
+        assert_eq!(first.materialize(&mut transactions, &rates(), &day(20)).unwrap(), 6);
+        assert_eq!(dates(&transactions, "netflix"), [day(2), day(9), day(16)]);
+        assert_eq!(first.materialize(&mut transactions, &rates(), &day(20)).unwrap(), 0);
+
+        // Without the saved last dates, as when the recurring file could not be written.
+        assert_eq!(recurring().materialize(&mut transactions, &rates(), &day(20)).unwrap(), 0);
+        assert_eq!(transactions.vec().len(), 6);
+
+        assert_eq!(first.materialize(&mut transactions, &rates(), &day(23)).unwrap(), 1);
+        assert_eq!(dates(&transactions, "netflix"), [day(2), day(9), day(16), day(23)]);
+    }
+
+    #[test]
+    fn missing_rate() {
+        let mut transactions = Transactions::new();
+        let mut recurring = recurring();
+        assert_eq!(recurring.materialize(&mut transactions, &Rates::default(), &day(20)).unwrap(), 3);
+        assert!(dates(&transactions, "netflix").is_empty());
+        assert_eq!(recurring.missing_rates(&Rates::default()), BTreeSet::from(["USD".to_string()]));
+
+        // Once the rate is given, the occurrences left behind are generated and converted.
+        assert_eq!(recurring.materialize(&mut transactions, &rates(), &day(20)).unwrap(), 3);
+        assert_eq!(dates(&transactions, "netflix"), [day(2), day(9), day(16)]);
+        assert!(recurring.missing_rates(&rates()).is_empty());
+        assert!(transactions.vec().iter().all(|tr| match tr {
+            Transaction::Purchase(purchase) if purchase.foreign.is_some() => purchase.amount == CentsAmount::new(450),
+            _ => true,
+        }));
+    }
+}
@@ -0,0 +1,483 @@
+use std::error;
+use std::fmt;
+
+/// Regular expressions for import rules, matched in time linear in the text
+/// by following all the paths through the compiled pattern at once.
+/// Supports literals, `.`, classes (`[a-z]`, `[^0-9]`, `\d`, `\w`, `\s` and
+/// their negations `\D`, `\W`, `\S`), anchors, groups, alternation, the `*`,
+/// `+`, `?` and `{n,m}` repetitions, and a leading `(?i)` for case-insensitive matching.
+#[derive(Debug, Clone)]
+pub struct Regex {
+    program: Vec<Inst>,
+    ignore_case: bool,
+}
+
+#[derive(Debug)]
+pub struct Error {
+    pattern: String,
+    reason: &'static str,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid pattern \"{}\": {}", self.pattern, self.reason)
+    }
+}
+
+impl error::Error for Error {}
+
+#[derive(Debug, Clone)]
+enum ClassItem {
+    Char(char),
+    Range(char, char),
+    Digit,
+    Word,
+    Space,
+    NotDigit,
+    NotWord,
+    NotSpace,
+}
+
+impl ClassItem {
+    fn matches(&self, c: char) -> bool {
+        match *self {
+            ClassItem::Char(x) => x == c,
+            ClassItem::Range(a, b) => (a..=b).contains(&c),
+            ClassItem::Digit => c.is_ascii_digit(),
+            ClassItem::Word => c.is_alphanumeric() || c == '_',
+            ClassItem::Space => c.is_whitespace(),
+            ClassItem::NotDigit => !c.is_ascii_digit(),
+            ClassItem::NotWord => !(c.is_alphanumeric() || c == '_'),
+            ClassItem::NotSpace => !c.is_whitespace(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Any,
+    Class(Vec<ClassItem>, bool),
+    Start,
+    End,
+    Group(Vec<Vec<Node>>),
+    Repeat(Box<Node>, usize, Option<usize>),
+}
+
+impl Node {
+    /// Number of instructions the node compiles to, saturated on overflow.
+    fn size(&self) -> usize {
+        match self {
+            Node::Any | Node::Class(..) | Node::Start | Node::End => 1,
+            Node::Group(alternatives) => alternatives_size(alternatives),
+            Node::Repeat(node, min, max) => {
+                let size = node.size();
+                let optional = match max {
+                    None => size.saturating_add(2),
+                    Some(max) => (max - min).saturating_mul(size.saturating_add(1)),
+                };
+                min.saturating_mul(size).saturating_add(optional)
+            },
+        }
+    }
+}
+
+fn alternatives_size(alternatives: &[Vec<Node>]) -> usize {
+    let nodes = alternatives.iter().flatten().fold(0, |acc: usize, node| acc.saturating_add(node.size()));
+    nodes.saturating_add(2 * (alternatives.len() - 1))
+}
+
+/// Limit on the size of the compiled pattern, only reached with large counted repetitions.
+const MAX_PROGRAM_LEN: usize = 10_000;
+
+struct Parser<'a> {
+    pattern: &'a str,
+    chars: Vec<char>,
+    pos: usize,
+    ignore_case: bool,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, reason: &'static str) -> Error {
+        Error{pattern: self.pattern.to_string(), reason}
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let ret = self.peek();
+        self.pos += 1;
+        ret
+    }
+
+    /// `c` as matched against the text, which is lowercased when ignoring case.
+    fn literal(&self, c: char) -> char {
+        let mut lower = c.to_lowercase();
+        match (self.ignore_case, lower.len()) {
+            (true, 1) => lower.next().unwrap(),
+            _ => c,
+        }
+    }
+
+    fn alternatives(&mut self) -> Result<Vec<Vec<Node>>, Error> {
+        let mut ret = vec![self.sequence()?];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            ret.push(self.sequence()?);
+        }
+        Ok(ret)
+    }
+
+    fn sequence(&mut self) -> Result<Vec<Node>, Error> {
+        let mut ret = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.atom()?;
+            ret.push(self.repetition(atom)?);
+        }
+        Ok(ret)
+    }
+
+    fn atom(&mut self) -> Result<Node, Error> {
+        match self.next().unwrap() {
+            '.' => Ok(Node::Any),
+            '^' => Ok(Node::Start),
+            '$' => Ok(Node::End),
+            '(' => {
+                let ret = self.alternatives()?;
+                match self.next() {
+                    Some(')') => Ok(Node::Group(ret)),
+                    _ => Err(self.error("unclosed group")),
+                }
+            },
+            '[' => self.class(),
+            '\\' => Ok(Node::Class(vec![self.escape()?], false)),
+            '*' | '+' | '?' | '{' => Err(self.error("nothing to repeat")),
+            c => Ok(Node::Class(vec![ClassItem::Char(self.literal(c))], false)),
+        }
+    }
+
+    fn escape(&mut self) -> Result<ClassItem, Error> {
+        match self.next() {
+            Some('d') => Ok(ClassItem::Digit),
+            Some('w') => Ok(ClassItem::Word),
+            Some('s') => Ok(ClassItem::Space),
+            Some('D') => Ok(ClassItem::NotDigit),
+            Some('W') => Ok(ClassItem::NotWord),
+            Some('S') => Ok(ClassItem::NotSpace),
+            Some('t') => Ok(ClassItem::Char('\t')),
+            Some('n') => Ok(ClassItem::Char('\n')),
+            Some('r') => Ok(ClassItem::Char('\r')),
+            Some(c) if !c.is_alphanumeric() => Ok(ClassItem::Char(self.literal(c))),
+            Some(_) => Err(self.error("unknown escape")),
+            None => Err(self.error("trailing backslash")),
+        }
+    }
+
+    fn class(&mut self) -> Result<Node, Error> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+
+        let mut items = Vec::new();
+        loop {
+            let item = match self.next() {
+                Some(']') if !items.is_empty() => return Ok(Node::Class(items, negated)),
+                Some('\\') => self.escape()?,
+                Some(c) => ClassItem::Char(self.literal(c)),
+                None => return Err(self.error("unclosed class")),
+            };
+            match (item, self.peek(), self.chars.get(self.pos + 1)) {
+                (ClassItem::Char(a), Some('-'), Some(&b)) if b != ']' => {
+                    self.pos += 2;
+                    let b = self.literal(b);
+                    if b < a {
+                        return Err(self.error("invalid range"));
+                    }
+                    items.push(ClassItem::Range(a, b));
+                },
+                (item, _, _) => items.push(item),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Option<usize> {
+        let begin = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        self.chars[begin..self.pos].iter().collect::<String>().parse().ok()
+    }
+
+    fn repetition(&mut self, atom: Node) -> Result<Node, Error> {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => {
+                self.pos += 1;
+                let min = self.number();
+                let max = if self.peek() == Some(',') {
+                    self.pos += 1;
+                    self.number()
+                } else {
+                    min
+                };
+                if self.peek() != Some('}') || (min.is_none() && max.is_none()) || max.is_some_and(|max| max < min.unwrap_or(0)) {
+                    return Err(self.error("invalid repetition"));
+                }
+                (min.unwrap_or(0), max)
+            },
+            _ => return Ok(atom),
+        };
+        self.pos += 1;
+        Ok(Node::Repeat(Box::new(atom), min, max))
+    }
+}
+
+/// Instruction of a compiled pattern, each thread of the matcher being at one of them.
+#[derive(Debug, Clone)]
+enum Inst {
+    Any,
+    Class(Vec<ClassItem>, bool),
+    Start,
+    End,
+    /// Continues at both targets.
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+#[derive(Default)]
+struct Compiler {
+    program: Vec<Inst>,
+}
+
+impl Compiler {
+    fn emit(&mut self, inst: Inst) -> usize {
+        self.program.push(inst);
+        self.program.len() - 1
+    }
+
+    fn alternatives(&mut self, alternatives: &[Vec<Node>]) {
+        let mut jumps = Vec::new();
+        let (last, rest) = alternatives.split_last().unwrap();
+        for sequence in rest {
+            let split = self.emit(Inst::Split(0, 0));
+            self.sequence(sequence);
+            jumps.push(self.emit(Inst::Jump(0)));
+            self.program[split] = Inst::Split(split + 1, self.program.len());
+        }
+        self.sequence(last);
+        for jump in jumps {
+            self.program[jump] = Inst::Jump(self.program.len());
+        }
+    }
+
+    fn sequence(&mut self, sequence: &[Node]) {
+        for node in sequence {
+            self.node(node);
+        }
+    }
+
+    fn node(&mut self, node: &Node) {
+        match node {
+            Node::Any => {
+                self.emit(Inst::Any);
+            },
+            Node::Class(items, negated) => {
+                self.emit(Inst::Class(items.clone(), *negated));
+            },
+            Node::Start => {
+                self.emit(Inst::Start);
+            },
+            Node::End => {
+                self.emit(Inst::End);
+            },
+            Node::Group(alternatives) => self.alternatives(alternatives),
+            Node::Repeat(node, min, max) => {
+                for _ in 0..*min {
+                    self.node(node);
+                }
+                match *max {
+                    None => {
+                        let split = self.emit(Inst::Split(0, 0));
+                        self.node(node);
+                        self.emit(Inst::Jump(split));
+                        self.program[split] = Inst::Split(split + 1, self.program.len());
+                    },
+                    Some(max) => {
+                        let splits: Vec<usize> = (*min..max).map(|_| {
+                            let split = self.emit(Inst::Split(0, 0));
+                            self.node(node);
+                            split
+                        }).collect();
+                        for split in splits {
+                            self.program[split] = Inst::Split(split + 1, self.program.len());
+                        }
+                    },
+                }
+            },
+        }
+    }
+}
+
+/// Instructions waiting for the next character, each at most once.
+struct Threads {
+    list: Vec<usize>,
+    seen: Vec<bool>,
+}
+
+impl Threads {
+    fn new(len: usize) -> Self {
+        Self{list: Vec::new(), seen: vec![false; len]}
+    }
+}
+
+impl Regex {
+    pub fn new(pattern: &str) -> Result<Self, Error> {
+        let (ignore_case, body) = match pattern.strip_prefix("(?i)") {
+            Some(body) => (true, body),
+            None => (false, pattern),
+        };
+
+        let mut parser = Parser{pattern, chars: body.chars().collect(), pos: 0, ignore_case};
+        let alternatives = parser.alternatives()?;
+        if parser.pos < parser.chars.len() {
+            return Err(parser.error("unmatched parenthesis"));
+        }
+        if alternatives_size(&alternatives) > MAX_PROGRAM_LEN {
+            return Err(parser.error("repetition too large"));
+        }
+
+        let mut compiler = Compiler::default();
+        compiler.alternatives(&alternatives);
+        compiler.emit(Inst::Match);
+
+        Ok(Self{program: compiler.program, ignore_case})
+    }
+
+    /// Whether the pattern matches somewhere in `text`.
+    pub fn is_match(&self, text: &str) -> bool {
+        let text: Vec<char> = if self.ignore_case { text.to_lowercase().chars().collect() } else { text.chars().collect() };
+        let mut current = Threads::new(self.program.len());
+        for pos in 0..=text.len() {
+            // A new thread at each position lets the match start anywhere.
+            if self.add_thread(&mut current, 0, &text, pos) {
+                return true;
+            }
+            let mut next = Threads::new(self.program.len());
+            for &pc in &current.list {
+                let matches = match &self.program[pc] {
+                    Inst::Any => pos < text.len(),
+                    Inst::Class(items, negated) => pos < text.len() && items.iter().any(|item| item.matches(text[pos])) != *negated,
+                    _ => false,
+                };
+                if matches && self.add_thread(&mut next, pc + 1, &text, pos + 1) {
+                    return true;
+                }
+            }
+            current = next;
+        }
+        false
+    }
+
+    /// Adds the instructions reachable from `pc` without consuming a character,
+    /// returning whether the end of the pattern is one of them.
+    fn add_thread(&self, threads: &mut Threads, pc: usize, text: &[char], pos: usize) -> bool {
+        let mut stack = vec![pc];
+        while let Some(pc) = stack.pop() {
+            if std::mem::replace(&mut threads.seen[pc], true) {
+                continue;
+            }
+            match self.program[pc] {
+                Inst::Any | Inst::Class(..) => threads.list.push(pc),
+                Inst::Start if pos == 0 => stack.push(pc + 1),
+                Inst::End if pos == text.len() => stack.push(pc + 1),
+                Inst::Start | Inst::End => (),
+                Inst::Split(first, second) => stack.extend([second, first]),
+                Inst::Jump(target) => stack.push(target),
+                Inst::Match => return true,
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, text: &str) -> bool {
+        Regex::new(pattern).unwrap().is_match(text)
+    }
+
+    #[test]
+    fn escapes() {
+        assert!(matches(r"^\d+$", "2024"));
+        assert!(!matches(r"\d", "abc"));
+        assert!(matches(r"^\D+$", "abc"));
+        assert!(matches(r"^\w+ \W \S+\s*$", "foo_1 - bar "));
+        assert!(!matches(r"^\S+$", "two words"));
+        assert!(matches(r"^[\S]+$", "oneword"));
+        assert!(matches(r"1\.5", "1.5"));
+        assert!(!matches(r"1\.5", "105"));
+        assert!(matches(r"a\tb", "a\tb"));
+        assert!(Regex::new(r"\q").is_err());
+        assert!(Regex::new(r"a\").is_err());
+    }
+
+    #[test]
+    fn case_folding() {
+        assert!(matches("(?i)AMAZON", "Order at amazon.com"));
+        assert!(matches("(?i)[A-Z]+ café", "SHOP CAFÉ"));
+        assert!(!matches("AMAZON", "amazon"));
+        assert!(matches(r"(?i)^\S+$", "NoSpaces"));
+        assert!(!matches(r"(?i)^\S+$", "Has Spaces"));
+        assert!(matches(r"(?i)^\D+$", "ABC"));
+        assert!(matches(r"(?i)\W", "A-B"));
+    }
+
+    #[test]
+    fn quantifiers() {
+        assert!(matches("^ab*c$", "ac"));
+        assert!(matches("^ab+c$", "abbbc"));
+        assert!(!matches("^ab+c$", "ac"));
+        assert!(matches("^colou?r$", "color"));
+        assert!(matches("^a{2,3}$", "aaa"));
+        assert!(!matches("^a{2,3}$", "aaaa"));
+        assert!(matches("^a{2}$", "aa"));
+        assert!(matches("^a{2,}$", "aaaaa"));
+        assert!(matches("^a{,3}$", ""));
+        assert!(!matches("^a{,3}$", "aaaa"));
+        assert!(Regex::new("a{}").is_err());
+        assert!(Regex::new("a{3,2}").is_err());
+        assert!(Regex::new("*a").is_err());
+        assert!(Regex::new("(a{1000}){1000}").is_err());
+    }
+
+    #[test]
+    fn groups_and_anchors() {
+        assert!(matches("^(rent|loyer) [0-9]{4}$", "loyer 2024"));
+        assert!(!matches("^(rent|loyer)$", "rental"));
+        assert!(matches("(ab)+$", "xabab"));
+        assert!(matches("", "anything"));
+        assert!(matches("[^a-c]", "abcd"));
+        assert!(!matches("[^a-c]", "abc"));
+        assert!(Regex::new("(a").is_err());
+        assert!(Regex::new("a)").is_err());
+        assert!(Regex::new("[z-a]").is_err());
+    }
+
+    #[test]
+    fn nested_repetitions_are_linear() {
+        let text = "a".repeat(1000);
+        assert!(!matches("^(a*)*b$", &text));
+        assert!(!matches("^(a|aa)+b$", &text));
+        assert!(matches("^(a*)*$", &text));
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::BTreeMap;
+use chrono::{Datelike, NaiveDate};
+
+use crate::tags::{Tags, TagRef};
+use crate::accounts::{Accounts, AccountRef};
+use crate::transaction::{Transactions, Transaction};
+use crate::money::{CentsAmount, SignedCentsAmount};
+use crate::datetime::Date;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Period {
+    Month,
+    Year,
+}
+
+impl Period {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "month" => Some(Period::Month),
+            "year" => Some(Period::Year),
+            _ => None,
+        }
+    }
+
+    /// First and last days of the period containing `date`.
+    pub fn range(self, date: &Date) -> (Date, Date) {
+        match self {
+            Period::Month => (date.first_of_month(), date.last_of_month()),
+            Period::Year => {
+                let year = date.naive().year();
+                (NaiveDate::from_ymd_opt(year, 1, 1).unwrap().into(), NaiveDate::from_ymd_opt(year, 12, 31).unwrap().into())
+            },
+        }
+    }
+
+    /// Last day of the period before the one containing `date`.
+    pub fn previous(self, date: &Date) -> Date {
+        self.range(date).0.pred()
+    }
+
+    /// First day of the period after the one containing `date`.
+    pub fn next(self, date: &Date) -> Date {
+        self.range(date).1.succ()
+    }
+
+    pub fn label(self, date: &Date) -> String {
+        match self {
+            Period::Month => date.naive().format("%B %Y").to_string(),
+            Period::Year => date.naive().format("%Y").to_string(),
+        }
+    }
+
+    pub fn toggle(self) -> Self {
+        match self {
+            Period::Month => Period::Year,
+            Period::Year => Period::Month,
+        }
+    }
+}
+
+/// Sums over a period of the transactions of a tag or an account.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Totals {
+    /// Total amount of the purchases.
+    pub spent: CentsAmount,
+    /// Share consumed, as a sum of internal deltas.
+    pub internal: SignedCentsAmount,
+    /// Amount paid out, as a sum of external deltas.
+    pub external: SignedCentsAmount,
+    /// Average daily flow.
+    pub flow: CentsAmount,
+}
+
+impl Totals {
+    pub fn zero() -> Self {
+        Self{spent: CentsAmount::new(0), internal: SignedCentsAmount::new(0), external: SignedCentsAmount::new(0), flow: CentsAmount::new(0)}
+    }
+
+    /// Change of each total since `previous`, in the order of the fields.
+    pub fn changes(&self, previous: &Totals) -> [SignedCentsAmount; 4] {
+        let unsigned = |current: CentsAmount, previous: CentsAmount| SignedCentsAmount::new(current.cents() as i64 - previous.cents() as i64);
+        let signed = |current: SignedCentsAmount, previous: SignedCentsAmount| SignedCentsAmount::new(current.cents() - previous.cents());
+        [
+            unsigned(self.spent, previous.spent),
+            signed(self.internal, previous.internal),
+            signed(self.external, previous.external),
+            unsigned(self.flow, previous.flow),
+        ]
+    }
+}
+
+impl std::ops::Add for Totals {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            spent: self.spent + other.spent,
+            internal: self.internal + other.internal,
+            external: self.external + other.external,
+            flow: self.flow + other.flow,
+        }
+    }
+}
+
+/// Totals of each tag, for the local account and rolled up to parents, and of each account.
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub from: Date,
+    pub to: Date,
+    pub tags: BTreeMap<TagRef, Totals>,
+    pub accounts: BTreeMap<AccountRef, Totals>,
+}
+
+/// Average of the daily flows of each tag from `from` to `to`, without rollup.
+fn average_flows(transactions: &Transactions, from: &Date, to: &Date, account: &AccountRef, tags: &Tags) -> BTreeMap<TagRef, CentsAmount> {
+    let days = transactions.daily_flows(from, to, account, tags);
+    let mut sums: BTreeMap<TagRef, u64> = BTreeMap::new();
+    for flows in &days {
+        for (tag, flow) in flows {
+            *sums.entry(tag.clone()).or_insert(0) += flow.cents();
+        }
+    }
+    sums.into_iter().map(|(tag, sum)| (tag, CentsAmount::new(sum.checked_div(days.len() as u64).unwrap_or(0)))).collect()
+}
+
+impl Report {
+    pub fn new(transactions: &Transactions, tags: &Tags, accounts: &Accounts, account: &AccountRef, from: &Date, to: &Date) -> Self {
+        let selected: Vec<&Transaction> = transactions.vec().iter().filter(|tr| from <= tr.date() && tr.date() <= to).collect();
+
+        let mut tag_totals: BTreeMap<TagRef, Totals> = BTreeMap::new();
+        for tr in &selected {
+            if let Transaction::Purchase(purchase) = tr {
+                let entry = tag_totals.entry(purchase.tag.clone()).or_insert_with(Totals::zero);
+                entry.spent += purchase.amount;
+                entry.internal = entry.internal + purchase.internal_delta(account);
+                entry.external = entry.external + purchase.external_delta(account);
+            }
+        }
+        for (tag, flow) in average_flows(transactions, from, to, account, tags) {
+            tag_totals.entry(tag).or_insert_with(Totals::zero).flow = flow;
+        }
+
+        let mut account_totals = BTreeMap::new();
+        for other in accounts.0.keys() {
+            let mut totals = Totals::zero();
+            for tr in &selected {
+                if let Transaction::Purchase(purchase) = tr {
+                    if &purchase.buyer == other {
+                        totals.spent += purchase.amount;
+                    }
+                }
+                totals.internal = totals.internal + tr.internal_delta(other);
+                totals.external = totals.external + tr.external_delta(other);
+            }
+            totals.flow = average_flows(transactions, from, to, other, tags).into_values().fold(CentsAmount::new(0), |acc, x| acc + x);
+            account_totals.insert(other.clone(), totals);
+        }
+
+        Self {
+            from: from.clone(),
+            to: to.clone(),
+            tags: tags.rollup(&tag_totals, Totals::zero()),
+            accounts: account_totals,
+        }
+    }
+
+    /// Reports of the period containing `date` and of the one before.
+    pub fn with_previous(period: Period, date: &Date, transactions: &Transactions, tags: &Tags, accounts: &Accounts, account: &AccountRef) -> (Self, Self) {
+        let (from, to) = period.range(date);
+        let (previous_from, previous_to) = period.range(&period.previous(date));
+        (Self::new(transactions, tags, accounts, account, &from, &to), Self::new(transactions, tags, accounts, account, &previous_from, &previous_to))
+    }
+}
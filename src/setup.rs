@@ -0,0 +1,352 @@
+use std::io::stdout;
+use std::collections::BTreeMap;
+
+use crate::term::*;
+use crate::interface::*;
+use crate::completion::Completor;
+use crate::color::RGBColor;
+use crate::tags::*;
+use crate::accounts::*;
+use crate::ledger::*;
+use crate::yamlrw::{self, YamlRW};
+
+const PALETTE: [&str; 6] = ["e06c75", "61afef", "98c379", "e5c07b", "c678dd", "56b6c2"];
+
+const SEED_TAGS: [(&str, usize); 6] = [
+    ("food", 7),
+    ("rent", 30),
+    ("transport", 7),
+    ("leisure", 14),
+    ("health", 30),
+    ("misc", 1),
+];
+
+/// Returns whether one of the configuration files of the ledger is missing.
+pub fn needed(dir: &LedgerDir) -> bool {
+    [LOCALCFG_FILE, TAGS_FILE, ACCOUNTS_FILE].iter().any(|file| !dir.file(file).exists())
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum SetupStep {
+    Accounts,
+    Local,
+    Tags,
+    Done,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum EntryFocus {
+    Name,
+    Value,
+}
+
+pub struct SetupWizard {
+    dir: LedgerDir,
+    step: SetupStep,
+    focus: EntryFocus,
+    accounts: Vec<(AccountRef, RGBColor)>,
+    account_name: CompletorInput,
+    account_color: ColorInput,
+    local: CompletorInput,
+    tags: Vec<(TagRef, usize)>,
+    tag_name: CompletorInput,
+    tag_dur: NumberInput,
+    write_accounts: bool,
+    write_local: bool,
+    write_tags: bool,
+}
+
+impl SetupWizard {
+    pub fn new(dir: LedgerDir) -> Result<Self, yamlrw::Error> {
+        let write_accounts = !dir.file(ACCOUNTS_FILE).exists();
+        let write_local = !dir.file(LOCALCFG_FILE).exists();
+        let write_tags = !dir.file(TAGS_FILE).exists();
+
+        let accounts = if write_accounts {
+            Vec::new()
+        } else {
+            Accounts::read_yaml(dir.file(ACCOUNTS_FILE))?.0.into_iter().map(|(name, data)| (name, data.color())).collect()
+        };
+
+        let mut ret = Self{
+            dir,
+            step: SetupStep::Accounts,
+            focus: EntryFocus::Name,
+            accounts,
+            account_name: CompletorInput::new('[', ']', false, Completor::new(Vec::new())),
+            account_color: ColorInput::new(None),
+            local: CompletorInput::new('[', ']', true, Completor::new(Vec::new())),
+            tags: SEED_TAGS.iter().map(|(tag, dur)| (tag.to_string(), *dur)).collect(),
+            tag_name: CompletorInput::new('<', '>', false, Completor::new(Vec::new())),
+            tag_dur: NumberInput::new(1, "days"),
+            write_accounts,
+            write_local,
+            write_tags,
+        };
+        ret.reset_account();
+        ret.reset_tag();
+        ret.enter_step(SetupStep::Accounts);
+        Ok(ret)
+    }
+
+    pub fn done(&self) -> bool {
+        self.step == SetupStep::Done
+    }
+
+    fn first_step(&self, step: SetupStep) -> SetupStep {
+        use SetupStep::*;
+        match step {
+            Accounts if !self.write_accounts => self.first_step(Local),
+            Local if !self.write_local => self.first_step(Tags),
+            Tags if !self.write_tags => Done,
+            step => step,
+        }
+    }
+
+    fn next_step(&mut self) {
+        use SetupStep::*;
+        let next = match self.step {
+            Accounts => Local,
+            Local => Tags,
+            Tags | Done => Done,
+        };
+        self.enter_step(next);
+    }
+
+    fn enter_step(&mut self, step: SetupStep) {
+        self.step = self.first_step(step);
+        self.focus = EntryFocus::Name;
+
+        if self.step == SetupStep::Local {
+            let names = self.accounts.iter().map(|(name, _)| name.clone()).collect();
+            self.local = CompletorInput::new('[', ']', true, Completor::new(names));
+        }
+    }
+
+    fn reset_account(&mut self) {
+        self.account_name.clear();
+        let color = RGBColor::from_hex(PALETTE[self.accounts.len() % PALETTE.len()]);
+        self.account_color = ColorInput::new(color);
+        self.focus = EntryFocus::Name;
+    }
+
+    fn reset_tag(&mut self) {
+        let seeds = SEED_TAGS.iter().map(|(tag, _)| tag.to_string()).filter(|tag| !self.tags.iter().any(|(x, _)| x == tag)).collect();
+        self.tag_name = CompletorInput::new('<', '>', false, Completor::new(seeds));
+        self.tag_dur.set(1);
+        self.focus = EntryFocus::Name;
+    }
+
+    fn validate_account(&mut self) {
+        let name = self.account_name.get();
+        if let Some(color) = self.account_color.get() {
+            if !self.accounts.iter().any(|(x, _)| x == &name) {
+                self.accounts.push((name, color));
+            }
+            self.reset_account();
+        }
+    }
+
+    fn validate_tag(&mut self) {
+        let name = self.tag_name.get();
+        if self.tag_dur.valid() {
+            if !self.tags.iter().any(|(x, _)| x == &name) {
+                self.tags.push((name, self.tag_dur.get()));
+            }
+            self.reset_tag();
+        }
+    }
+
+    pub fn save(&self) -> Result<(), yamlrw::Error> {
+        std::fs::create_dir_all(self.dir.path()).map_err(|err| yamlrw::Error::FileError(self.dir.path().display().to_string(), err))?;
+
+        if self.write_accounts {
+            let accounts: BTreeMap<AccountRef, AccountData> = self.accounts.iter().map(|(name, color)| (name.clone(), AccountData::new(*color))).collect();
+            Accounts(accounts).write_yaml(self.dir.file(ACCOUNTS_FILE))?;
+        }
+
+        if self.write_local {
+            LocalCfg::new(self.local.get()).write_yaml(self.dir.file(LOCALCFG_FILE))?;
+        }
+
+        if self.write_tags {
+            let tags: BTreeMap<TagRef, TagData> = self.tags.iter().map(|(name, dur)| (name.clone(), TagData::new(*dur))).collect();
+            Tags(tags).write_yaml(self.dir.file(TAGS_FILE))?;
+        }
+
+        Ok(())
+    }
+
+    fn title(&self) -> &'static str {
+        use SetupStep::*;
+        match self.step {
+            Accounts => "Accounts: name and color of each account sharing expenses",
+            Local => "Local account: the account using this computer",
+            Tags => "Tags: name and smoothing duration of each expense kind",
+            Done => "",
+        }
+    }
+
+    fn help(&self) -> &'static str {
+        use SetupStep::*;
+        match self.step {
+            Accounts | Tags => "Enter: add, Tab on empty name: next step, Ctrl-d: remove last, Esc: quit",
+            Local => "Enter: confirm, Esc: quit",
+            Done => "",
+        }
+    }
+
+    fn entries(&self) -> Vec<String> {
+        use SetupStep::*;
+        match self.step {
+            Accounts => self.accounts.iter().map(|(name, color)| format!("{name} #{color}")).collect(),
+            Tags => self.tags.iter().map(|(name, dur)| format!("{name} ({dur} days)")).collect(),
+            Local | Done => Vec::new(),
+        }
+    }
+
+    fn name_input(&self) -> &CompletorInput {
+        use SetupStep::*;
+        match self.step {
+            Accounts => &self.account_name,
+            Tags => &self.tag_name,
+            Local | Done => &self.local,
+        }
+    }
+
+    fn value_input(&self) -> Option<&dyn TermElement> {
+        use SetupStep::*;
+        match self.step {
+            Accounts => Some(&self.account_color),
+            Tags => Some(&self.tag_dur),
+            Local | Done => None,
+        }
+    }
+
+    fn entry_top(&self, element_box: TermBox) -> usize {
+        usize::min(element_box.top + 3 + self.entries().len(), element_box.bottom.saturating_sub(1))
+    }
+
+    fn name_box(&self, element_box: TermBox) -> TermBox {
+        let top = self.entry_top(element_box);
+        TermBox{left: element_box.left, right: element_box.right, top, bottom: top+1}
+    }
+
+    fn value_box(&self, element_box: TermBox) -> TermBox {
+        let top = self.entry_top(element_box);
+        let left = usize::min(element_box.left + self.name_input().display_len() + 1, element_box.right);
+        TermBox{left, right: element_box.right, top, bottom: top+1}
+    }
+
+    fn focused(&self, element_box: TermBox) -> (&dyn TermElement, TermBox) {
+        match (self.focus, self.value_input()) {
+            (EntryFocus::Value, Some(value)) => (value, self.value_box(element_box)),
+            _ => (self.name_input(), self.name_box(element_box)),
+        }
+    }
+}
+
+impl TermElement for SetupWizard {
+    fn display(&self, element_box: TermBox, _active: bool) -> crossterm::Result<()> {
+        use crossterm::{
+            queue,
+            style::{PrintStyledContent, Color},
+        };
+
+        TermPos::new(element_box.left, element_box.top).goto()?;
+        queue!(stdout(), PrintStyledContent(simple_stylize(truncate_align_left(self.title(), element_box.width()), Color::Reset, true, false)))?;
+        TermPos::new(element_box.left, element_box.top+1).goto()?;
+        queue!(stdout(), PrintStyledContent(simple_stylize(truncate_align_left(self.help(), element_box.width()), Color::Reset, false, false)))?;
+
+        for (row, entry) in ((element_box.top+3)..self.entry_top(element_box)).zip(self.entries()) {
+            TermPos::new(element_box.left, row).goto()?;
+            queue!(stdout(), PrintStyledContent(simple_stylize(truncate_align_left(&entry, element_box.width()), Color::Reset, false, false)))?;
+        }
+
+        self.name_input().display(self.name_box(element_box), self.focus == EntryFocus::Name)?;
+        if let Some(value) = self.value_input() {
+            value.display(self.value_box(element_box), self.focus == EntryFocus::Value)?;
+        }
+
+        Ok(())
+    }
+
+    fn popup(&self, element_box: TermBox, window_box: TermBox) -> crossterm::Result<()> {
+        let (child, child_box) = self.focused(element_box);
+        child.popup(child_box, window_box)
+    }
+
+    fn set_cursor(&self, element_box: TermBox, window_box: TermBox) -> crossterm::Result<()> {
+        let (child, child_box) = self.focused(element_box);
+        child.set_cursor(child_box, window_box)
+    }
+
+    fn input(&mut self, event: InputEvent) -> Option<InputEvent> {
+        use InputEvent::*;
+
+        if let Esc = event {
+            return Some(Esc);
+        }
+
+        match (self.step, self.focus) {
+            (SetupStep::Accounts, EntryFocus::Name) => {
+                match self.account_name.input(event) {
+                    Some(Tab | Enter) if self.account_name.is_empty() && !self.accounts.is_empty() => self.next_step(),
+                    Some(Tab | Enter) if self.account_name.is_empty() => (),
+                    Some(Tab | Enter) => self.focus = EntryFocus::Value,
+                    Some(Ctrl('d')) => {
+                        self.accounts.pop();
+                        self.reset_account();
+                    },
+                    _ => (),
+                }
+            },
+            (SetupStep::Accounts, EntryFocus::Value) => {
+                match self.account_color.input(event) {
+                    Some(Tab | Enter) => self.validate_account(),
+                    Some(BackTab) => self.focus = EntryFocus::Name,
+                    _ => (),
+                }
+            },
+            (SetupStep::Local, _) => {
+                if let Some(Tab | Enter) = self.local.input(event) {
+                    if self.local.valid() {
+                        self.next_step();
+                    }
+                }
+            },
+            (SetupStep::Tags, EntryFocus::Name) => {
+                match self.tag_name.input(event) {
+                    Some(Tab | Enter) if self.tag_name.is_empty() && !self.tags.is_empty() => self.next_step(),
+                    Some(Tab | Enter) if self.tag_name.is_empty() => (),
+                    Some(Tab | Enter) => {
+                        let name = self.tag_name.get();
+                        if let Some((_, dur)) = SEED_TAGS.iter().find(|(tag, _)| tag == &name) {
+                            self.tag_dur.set(*dur);
+                        }
+                        self.focus = EntryFocus::Value;
+                    },
+                    Some(Ctrl('d')) => {
+                        self.tags.pop();
+                        self.reset_tag();
+                    },
+                    _ => (),
+                }
+            },
+            (SetupStep::Tags, EntryFocus::Value) => {
+                match self.tag_dur.input(event) {
+                    Some(Tab | Enter) => self.validate_tag(),
+                    Some(BackTab) => self.focus = EntryFocus::Name,
+                    _ => (),
+                }
+            },
+            (SetupStep::Done, _) => (),
+        }
+
+        if self.done() {
+            Some(Enter)
+        } else {
+            None
+        }
+    }
+}
@@ -1,24 +1,54 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::error;
+use std::fmt;
 use serde::{Serialize, Deserialize};
 
 use crate::yamlrw::YamlRW;
+use crate::money::CentsAmount;
 
 pub type TagRef = String;
 
+#[derive(Debug)]
+pub enum Error {
+    Cycle(Vec<TagRef>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Cycle(ref tags) =>
+                write!(f, "Tag hierarchy contains a cycle: {}", tags.join(" -> ")),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TagData {
     dur: usize,
     parent: Option<TagRef>,
+    /// Amount the local account intends to consume of the tag and its descendants each month.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    budget: Option<CentsAmount>,
 }
 
 impl TagData {
     pub fn new(dur: usize) -> Self {
-        Self{dur, parent: None}
+        Self{dur, parent: None, budget: None}
     }
 
     pub fn dur(&self) -> usize {
         self.dur
     }
+
+    pub fn parent(&self) -> Option<&TagRef> {
+        self.parent.as_ref()
+    }
+
+    pub fn budget(&self) -> Option<CentsAmount> {
+        self.budget
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -27,13 +57,84 @@ pub struct Tags(pub BTreeMap<TagRef, TagData>);
 impl YamlRW for Tags {}
 
 impl Tags {
-    pub fn fix(&mut self) {
+    pub fn fix(&mut self) -> Result<(), Error> {
         for data in self.0.clone().into_values() {
-            if let TagData{dur, parent: Some(parent)} = data {
-                if !self.0.contains_key(&parent) {
-                    self.0.insert(parent, TagData::new(dur));
+            if let TagData{dur, parent: Some(parent), ..} = data {
+                self.0.entry(parent).or_insert_with(|| TagData::new(dur));
+            }
+        }
+
+        for tag in self.0.keys() {
+            let mut path = vec![tag.clone()];
+            let mut visited = BTreeSet::from([tag]);
+            let mut curr = tag;
+            while let Some(parent) = self.parent(curr) {
+                path.push(parent.clone());
+                if !visited.insert(parent) {
+                    let start = path.iter().position(|x| x == parent).unwrap();
+                    return Err(Error::Cycle(path.split_off(start)));
+                }
+                curr = parent;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn parent(&self, tag: &TagRef) -> Option<&TagRef> {
+        self.0.get(tag).and_then(TagData::parent)
+    }
+
+    /// Parents of `tag`, nearest first.
+    pub fn ancestors(&self, tag: &TagRef) -> Vec<TagRef> {
+        let mut ret = Vec::new();
+        let mut curr = tag;
+        while let Some(parent) = self.parent(curr) {
+            ret.push(parent.clone());
+            curr = parent;
+        }
+        ret
+    }
+
+    /// Whether `tag` is `ancestor` or one of its descendants.
+    pub fn is_descendant(&self, tag: &TagRef, ancestor: &TagRef) -> bool {
+        tag == ancestor || self.ancestors(tag).contains(ancestor)
+    }
+
+    /// Children of `tag`, or root tags when `tag` is `None`.
+    pub fn children(&self, tag: Option<&TagRef>) -> Vec<TagRef> {
+        self.0.iter().filter(|(_, data)| data.parent() == tag).map(|(child, _)| child.clone()).collect()
+    }
+
+    pub fn depth(&self, tag: &TagRef) -> usize {
+        self.ancestors(tag).len()
+    }
+
+    /// All tags in depth-first order, skipping the descendants of `collapsed`.
+    pub fn tree(&self, collapsed: &BTreeSet<TagRef>) -> Vec<TagRef> {
+        fn walk(tags: &Tags, tag: Option<&TagRef>, collapsed: &BTreeSet<TagRef>, ret: &mut Vec<TagRef>) {
+            for child in tags.children(tag) {
+                ret.push(child.clone());
+                if !collapsed.contains(&child) {
+                    walk(tags, Some(&child), collapsed, ret);
                 }
             }
         }
+
+        let mut ret = Vec::new();
+        walk(self, None, collapsed, &mut ret);
+        ret
+    }
+
+    /// Adds the value of each tag to all of its ancestors.
+    pub fn rollup<T: Copy + std::ops::Add<Output = T>>(&self, values: &BTreeMap<TagRef, T>, zero: T) -> BTreeMap<TagRef, T> {
+        let mut ret: BTreeMap<TagRef, T> = self.0.keys().map(|tag| (tag.clone(), zero)).collect();
+        for (tag, value) in values {
+            for target in std::iter::once(tag.clone()).chain(self.ancestors(tag)) {
+                let entry = ret.entry(target).or_insert(zero);
+                *entry = *entry + *value;
+            }
+        }
+        ret
     }
 }
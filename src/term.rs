@@ -88,7 +88,7 @@ pub fn subdiv_flex<const SIZE: usize>(total: usize, weights: [usize; SIZE]) -> [
 
 pub fn subdiv_const_flex<const SIZE: usize>(total: usize, weights: [(usize, usize); SIZE]) -> [usize; SIZE] {
     assert!(SIZE > 0);
-    let const_widths = weights.clone().map(|(c, _)| c);
+    let const_widths = weights.map(|(c, _)| c);
     let const_width: usize = const_widths.iter().sum();
     assert!(total > const_width);
     let flex_width: usize = total - const_width;
@@ -102,14 +102,12 @@ pub fn subdiv_const_flex<const SIZE: usize>(total: usize, weights: [(usize, usiz
 }
 
 pub fn truncate_align_left(text: &str, width: usize) -> String {
-    let mut text = text.to_string();
-    text.truncate(width);
+    let text: String = text.chars().take(width).collect();
     format!("{: <width$}", text, width = width)
 }
 
 pub fn truncate_align_center(text: &str, width: usize) -> String {
-    let mut text = text.to_string();
-    text.truncate(width);
+    let text: String = text.chars().take(width).collect();
     format!("{: ^width$}", text, width = width)
 }
 
@@ -119,6 +117,8 @@ pub enum InputEvent {
     Down,
     Left,
     Right,
+    PageUp,
+    PageDown,
     Esc,
     Backspace,
     Delete,
@@ -126,6 +126,7 @@ pub enum InputEvent {
     BackTab,
     Enter,
     Char(char),
+    Ctrl(char),
 }
 
 pub fn get_event() -> crossterm::Result<InputEvent> {
@@ -143,16 +144,20 @@ pub fn get_event() -> crossterm::Result<InputEvent> {
                             KeyCode::Down => return Ok(InputEvent::Down),
                             KeyCode::Left => return Ok(InputEvent::Left),
                             KeyCode::Right => return Ok(InputEvent::Right),
+                            KeyCode::PageUp => return Ok(InputEvent::PageUp),
+                            KeyCode::PageDown => return Ok(InputEvent::PageDown),
                             KeyCode::Backspace => return Ok(InputEvent::Backspace),
                             KeyCode::Delete => return Ok(InputEvent::Delete),
                             KeyCode::Tab => return Ok(InputEvent::Tab),
                             _ => ()
                         }
                     },
-                    KeyModifiers::SHIFT => {
-                        match key_event.code {
-                            KeyCode::BackTab => return Ok(InputEvent::BackTab),
-                            _ => ()
+                    KeyModifiers::SHIFT if key_event.code == KeyCode::BackTab => {
+                        return Ok(InputEvent::BackTab);
+                    },
+                    KeyModifiers::CONTROL => {
+                        if let KeyCode::Char(c) = key_event.code {
+                            return Ok(InputEvent::Ctrl(c));
                         }
                     },
                     _ => (),
@@ -1,12 +1,15 @@
 use std::collections::BTreeMap;
+use std::cell::RefCell;
 use serde::{Serialize, Deserialize};
 
 use crate::tags::*;
 use crate::accounts::AccountRef;
 use crate::money::*;
+use crate::currency::ForeignAmount;
 use crate::moneystate::*;
 use crate::datetime::Date;
-use crate::yamlrw::YamlRW;
+use crate::yamlrw::{self, YamlRW};
+use crate::ledger::{LedgerDir, DATA_FILE};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Consumers(pub BTreeMap<AccountRef, usize>);
@@ -21,16 +24,25 @@ impl Consumers {
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Purchase {
     pub date: Date,
+    /// In the ledger currency, converted from `foreign` when it is set.
     pub amount: CentsAmount,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub foreign: Option<ForeignAmount>,
     pub desc: String,
     pub tag: TagRef,
     pub buyer: AccountRef,
     pub consumers: Consumers,
+    /// Name of the recurring definition this purchase was generated from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recurring: Option<String>,
+    /// Identifier given by the bank to the statement line this purchase was imported from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub import_id: Option<String>,
 }
 
 impl Purchase {
     pub fn internal_delta(&self, account: &AccountRef) -> SignedCentsAmount {
-        self.consumers.amounts(self.amount).get(account).cloned().map(|x| SignedCentsAmount::negative(x)).unwrap_or(SignedCentsAmount::new(0))
+        self.consumers.amounts(self.amount).get(account).cloned().map(SignedCentsAmount::negative).unwrap_or(SignedCentsAmount::new(0))
     }
 
     pub fn external_delta(&self, account: &AccountRef) -> SignedCentsAmount {
@@ -48,39 +60,76 @@ impl Purchase {
 
 
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Transfer {
+    pub date: Date,
+    pub from: AccountRef,
+    pub to: AccountRef,
+    pub amount: CentsAmount,
+    pub desc: String,
+}
+
+impl Transfer {
+    pub fn internal_delta(&self, _account: &AccountRef) -> SignedCentsAmount {
+        SignedCentsAmount::new(0)
+    }
+
+    pub fn external_delta(&self, account: &AccountRef) -> SignedCentsAmount {
+        if &self.from == account && &self.to != account {
+            SignedCentsAmount::positive(self.amount)
+        } else if &self.to == account && &self.from != account {
+            SignedCentsAmount::negative(self.amount)
+        } else {
+            SignedCentsAmount::new(0)
+        }
+    }
+
+    pub fn internal_flow(&self, _account: &AccountRef) -> SignedFlow {
+        SignedFlow(SignedCentsAmount::new(0))
+    }
+}
+
+
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum Transaction {
     Purchase(Purchase),
+    Transfer(Transfer),
 }
 
 impl Transaction {
     pub fn date(&self) -> &Date {
         match &self {
             Transaction::Purchase(purchase) => &purchase.date,
+            Transaction::Transfer(transfer) => &transfer.date,
         }
     }
 
     pub fn abs_amount(&self) -> CentsAmount {
         match &self {
             Transaction::Purchase(purchase) => purchase.amount,
+            Transaction::Transfer(transfer) => transfer.amount,
         }
     }
 
     pub fn internal_delta(&self, account: &AccountRef) -> SignedCentsAmount {
         match &self {
             Transaction::Purchase(purchase) => purchase.internal_delta(account),
+            Transaction::Transfer(transfer) => transfer.internal_delta(account),
         }
     }
 
     pub fn external_delta(&self, account: &AccountRef) -> SignedCentsAmount {
         match &self {
             Transaction::Purchase(purchase) => purchase.external_delta(account),
+            Transaction::Transfer(transfer) => transfer.external_delta(account),
         }
     }
 
     pub fn internal_flow(&self, account: &AccountRef, tags: &Tags, transactions: &Transactions) -> SignedFlow {
         match &self {
             Transaction::Purchase(purchase) => purchase.internal_flow(account, tags, transactions),
+            Transaction::Transfer(transfer) => transfer.internal_flow(account),
         }
     }
 
@@ -89,7 +138,13 @@ impl Transaction {
         match &self {
             Transaction::Purchase(purchase) => {
                 ret.push(purchase.buyer.clone());
-                ret.append(&mut purchase.consumers.0.keys().cloned().filter(|x| x != &purchase.buyer).collect());
+                ret.append(&mut purchase.consumers.0.keys().filter(|&x| x != &purchase.buyer).cloned().collect());
+            },
+            Transaction::Transfer(transfer) => {
+                ret.push(transfer.from.clone());
+                if transfer.to != transfer.from {
+                    ret.push(transfer.to.clone());
+                }
             },
         }
         ret
@@ -98,89 +153,336 @@ impl Transaction {
     pub fn desc(&self) -> &String {
         match &self {
             Transaction::Purchase(purchase) => &purchase.desc,
+            Transaction::Transfer(transfer) => &transfer.desc,
+        }
+    }
+
+    pub fn foreign(&self) -> Option<&ForeignAmount> {
+        match &self {
+            Transaction::Purchase(purchase) => purchase.foreign.as_ref(),
+            Transaction::Transfer(_) => None,
+        }
+    }
+
+    pub fn recurring(&self) -> Option<&String> {
+        match &self {
+            Transaction::Purchase(purchase) => purchase.recurring.as_ref(),
+            Transaction::Transfer(_) => None,
         }
     }
 
     pub fn kind_str(&self) -> String {
         match &self {
-            Transaction::Purchase(purchase) => format!("{}", purchase.tag),
+            Transaction::Purchase(purchase) => purchase.tag.to_string(),
+            Transaction::Transfer(_) => "transfer".to_string(),
         }
     }
 }
 
+/// Flow snapshots taken after the last transaction of each date, per account.
+/// Filled lazily and truncated from the first modified date onward.
+#[derive(Debug, Clone, Default)]
+struct FlowCache(BTreeMap<AccountRef, BTreeMap<Date, FlowStatesSnapshot>>);
+
+impl FlowCache {
+    fn invalidate_from(&mut self, date: &Date) {
+        for checkpoints in self.0.values_mut() {
+            checkpoints.split_off(date);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Transactions(Vec<Transaction>);
+#[serde(transparent)]
+pub struct Transactions {
+    list: Vec<Transaction>,
+    #[serde(skip)]
+    flows: RefCell<FlowCache>,
+}
 
 impl Transactions {
     pub fn new() -> Self {
-        Self(Vec::new())
+        Self{list: Vec::new(), flows: RefCell::new(FlowCache::default())}
+    }
+
+    /// Reads the data file of the ledger, empty if it does not exist yet.
+    /// Any other failure is an error, so that the file is not overwritten later.
+    pub fn load(dir: &LedgerDir) -> Result<Self, yamlrw::Error> {
+        let path = dir.file(DATA_FILE);
+        let mut ret = if path.exists() {
+            Self::read_yaml(path)?
+        } else {
+            Self::new()
+        };
+        ret.fix();
+        Ok(ret)
     }
 
     pub fn fix(&mut self) {
-        self.0.sort_by(|a, b| a.date().cmp(b.date()));
+        self.list.sort_by(|a, b| a.date().cmp(b.date()));
+        self.flows.get_mut().clear();
     }
 
     pub fn add(&mut self, transaction: Transaction) -> usize {
-        let index = self.0.partition_point(|tr| tr.date() <= transaction.date());
-        self.0.insert(index, transaction);
+        let index = self.list.partition_point(|tr| tr.date() <= transaction.date());
+        self.flows.get_mut().invalidate_from(transaction.date());
+        self.list.insert(index, transaction);
         index
     }
 
     pub fn remove(&mut self, index: usize) {
-        self.0.remove(index);
+        let transaction = self.list.remove(index);
+        self.flows.get_mut().invalidate_from(transaction.date());
+    }
+
+    pub fn replace(&mut self, index: usize, transaction: Transaction) -> usize {
+        self.remove(index);
+        self.add(transaction)
     }
 
     pub fn len(&self) -> usize {
-        self.0.len()
+        self.list.len()
     }
 
     pub fn vec(&self) -> &Vec<Transaction> {
-        &self.0
+        &self.list
     }
 
-    pub fn initial_snapshot(&self, tags: &Tags) -> FlowStatesSnapshot {
-        FlowStatesSnapshot::new(self.0.get(0).map_or_else(|| Date::today(), |x| x.date().clone()), tags)
+    pub fn balances(&self) -> Balances {
+        let mut ret = Balances::new();
+        for tr in &self.list {
+            ret.add(tr);
+        }
+        ret
     }
 
-    pub fn snapshot_before(&self, date: &Date, account: &AccountRef, tags: &Tags) -> FlowStatesSnapshot {
-        let mut ret = self.initial_snapshot(tags);
-
-        for tr in &self.0 {
-            if tr.date() >= date {
-                break;
+    /// Share of each tag consumed by `account`, without rollup.
+    pub fn spending(&self, account: &AccountRef) -> BTreeMap<TagRef, SignedCentsAmount> {
+        let mut ret = BTreeMap::new();
+        for tr in &self.list {
+            if let Transaction::Purchase(purchase) = tr {
+                let entry = ret.entry(purchase.tag.clone()).or_insert(SignedCentsAmount::new(0));
+                *entry = *entry + purchase.internal_delta(account);
             }
+        }
+        ret
+    }
 
-            match tr {
-                Transaction::Purchase(purchase) => {
-                    ret.add(purchase, account, tags);
-                },
+    /// Descriptions of the purchases, the most used first, older uses counting for less.
+    pub fn purchase_descriptions(&self, today: &Date) -> Vec<String> {
+        let mut scores: BTreeMap<&String, f64> = BTreeMap::new();
+        for tr in &self.list {
+            if let Transaction::Purchase(purchase) = tr {
+                if purchase.desc.is_empty() {
+                    continue;
+                }
+                // The weight of a use halves every 90 days.
+                let age = (today.naive() - purchase.date.naive()).num_days().max(0) as f64;
+                *scores.entry(&purchase.desc).or_insert(0.0) += 0.5f64.powf(age / 90.0);
             }
         }
+        let mut ranked: Vec<(&String, f64)> = scores.into_iter().collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranked.into_iter().map(|(desc, _)| desc.clone()).collect()
+    }
 
-        ret.forward(&date);
+    /// Most recent purchase with each description.
+    pub fn latest_purchases(&self) -> BTreeMap<String, Purchase> {
+        let mut ret = BTreeMap::new();
+        for tr in &self.list {
+            if let Transaction::Purchase(purchase) = tr {
+                ret.insert(purchase.desc.clone(), purchase.clone());
+            }
+        }
+        ret
+    }
 
+    /// Share of each tag consumed by `account` from `from` to `to` included, without rollup.
+    pub fn spending_between(&self, from: &Date, to: &Date, account: &AccountRef) -> BTreeMap<TagRef, SignedCentsAmount> {
+        let mut ret = BTreeMap::new();
+        for tr in self.list.iter().filter(|tr| from <= tr.date() && tr.date() <= to) {
+            if let Transaction::Purchase(purchase) = tr {
+                let entry = ret.entry(purchase.tag.clone()).or_insert(SignedCentsAmount::new(0));
+                *entry = *entry + purchase.internal_delta(account);
+            }
+        }
         ret
     }
 
-    pub fn snapshot_after(&self, date: &Date, account: &AccountRef, tags: &Tags) -> FlowStatesSnapshot {
-        let mut ret = self.initial_snapshot(tags);
+    /// Current flow of each tag for `account` on `date`, without rollup.
+    pub fn flows_at(&self, date: &Date, account: &AccountRef, tags: &Tags) -> BTreeMap<TagRef, CentsAmount> {
+        if self.list.first().is_none_or(|first| date < first.date()) {
+            return tags.0.keys().map(|tag| (tag.clone(), CentsAmount::new(0))).collect();
+        }
+        self.snapshot_after(date, account, tags).state().0.iter().map(|(tag, state)| (tag.clone(), state.flow().0)).collect()
+    }
+
+    /// Flow of each tag for `account` on every day from `from` to `to`, without rollup.
+    pub fn daily_flows(&self, from: &Date, to: &Date, account: &AccountRef, tags: &Tags) -> Vec<BTreeMap<TagRef, CentsAmount>> {
+        let mut ret = Vec::new();
+        let mut date = from.clone();
+
+        while &date <= to && self.list.first().is_none_or(|first| &date < first.date()) {
+            ret.push(self.flows_at(&date, account, tags));
+            date.incr();
+        }
+        if &date > to {
+            return ret;
+        }
 
-        for tr in &self.0 {
-            if tr.date() > date {
-                break;
+        let mut snapshot = self.snapshot_after(&date, account, tags);
+        let mut index = self.list.partition_point(|tr| tr.date() <= &date);
+        loop {
+            ret.push(snapshot.state().0.iter().map(|(tag, state)| (tag.clone(), state.flow().0)).collect());
+
+            date.incr();
+            if &date > to {
+                return ret;
             }
 
+            snapshot.forward(&date);
+            while let Some(tr) = self.list.get(index).filter(|tr| tr.date() == &date) {
+                if let Transaction::Purchase(purchase) = tr {
+                    snapshot.add(purchase, account, tags);
+                }
+                index += 1;
+            }
+        }
+    }
+
+    pub fn initial_snapshot(&self, tags: &Tags) -> FlowStatesSnapshot {
+        FlowStatesSnapshot::new(self.list.first().map_or_else(Date::today, |x| x.date().clone()), tags)
+    }
+
+    /// Extends the cached checkpoints of `account` up to the last transaction.
+    fn update_flows(&self, account: &AccountRef, tags: &Tags) {
+        let mut flows = self.flows.borrow_mut();
+        let checkpoints = flows.0.entry(account.clone()).or_default();
+
+        let (mut snapshot, start) = match checkpoints.last_key_value() {
+            Some((date, snapshot)) => (snapshot.clone(), self.list.partition_point(|tr| tr.date() <= date)),
+            None => (self.initial_snapshot(tags), 0),
+        };
+
+        for (index, tr) in self.list.iter().enumerate().skip(start) {
             match tr {
                 Transaction::Purchase(purchase) => {
-                    ret.add(purchase, account, tags);
+                    snapshot.add(purchase, account, tags);
+                },
+                Transaction::Transfer(transfer) => {
+                    snapshot.forward(&transfer.date);
                 },
             }
+
+            if self.list.get(index + 1).is_none_or(|next| next.date() != tr.date()) {
+                checkpoints.insert(tr.date().clone(), snapshot.clone());
+            }
         }
+    }
 
+    fn snapshot_from(&self, checkpoint: Option<FlowStatesSnapshot>, date: &Date, tags: &Tags) -> FlowStatesSnapshot {
+        let mut ret = checkpoint.unwrap_or_else(|| self.initial_snapshot(tags));
         ret.forward(date);
-
         ret
     }
+
+    pub fn snapshot_before(&self, date: &Date, account: &AccountRef, tags: &Tags) -> FlowStatesSnapshot {
+        self.update_flows(account, tags);
+        let checkpoint = self.flows.borrow().0[account].range(..date).next_back().map(|(_, snapshot)| snapshot.clone());
+        self.snapshot_from(checkpoint, date, tags)
+    }
+
+    pub fn snapshot_after(&self, date: &Date, account: &AccountRef, tags: &Tags) -> FlowStatesSnapshot {
+        self.update_flows(account, tags);
+        let checkpoint = self.flows.borrow().0[account].range(..=date).next_back().map(|(_, snapshot)| snapshot.clone());
+        self.snapshot_from(checkpoint, date, tags)
+    }
 }
 
 impl YamlRW for Transactions {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn day(day: u32) -> Date {
+        NaiveDate::from_ymd_opt(2026, 3, day).unwrap().into()
+    }
+
+    fn purchase(date: u32, cents: u64, tag: &str) -> Transaction {
+        Transaction::Purchase(Purchase {
+            date: day(date),
+            amount: CentsAmount::new(cents),
+            foreign: None,
+            desc: format!("{tag} {date}"),
+            tag: tag.to_string(),
+            buyer: "alice".to_string(),
+            consumers: Consumers([("alice".to_string(), 1), ("bob".to_string(), 2)].into_iter().collect()),
+            recurring: None,
+            import_id: None,
+        })
+    }
+
+    fn tags() -> Tags {
+        Tags([("food".to_string(), TagData::new(7)), ("rent".to_string(), TagData::new(30))].into_iter().collect())
+    }
+
+    /// Flows on `date` computed from the first transaction, without the cache.
+    fn replayed(transactions: &Transactions, date: &Date, account: &AccountRef, tags: &Tags) -> BTreeMap<TagRef, CentsAmount> {
+        let mut snapshot = transactions.initial_snapshot(tags);
+        if date < snapshot.date() {
+            return tags.0.keys().map(|tag| (tag.clone(), CentsAmount::new(0))).collect();
+        }
+        for tr in transactions.vec().iter().filter(|tr| tr.date() <= date) {
+            if let Transaction::Purchase(purchase) = tr {
+                snapshot.add(purchase, account, tags);
+            }
+        }
+        snapshot.forward(date);
+        snapshot.state().0.iter().map(|(tag, state)| (tag.clone(), state.flow().0)).collect()
+    }
+
+    fn assert_cache_matches(transactions: &Transactions, tags: &Tags) {
+        for account in ["alice".to_string(), "bob".to_string()] {
+            let expected: Vec<_> = (1..=31).map(|date| replayed(transactions, &day(date), &account, tags)).collect();
+            for date in (1..=31).rev() {
+                assert_eq!(transactions.flows_at(&day(date), &account, tags), expected[date as usize - 1], "{account} on day {date}");
+            }
+            assert_eq!(transactions.daily_flows(&day(1), &day(31), &account, tags), expected, "{account}");
+        }
+    }
+
+    #[test]
+    fn cached_flows() {
+        let tags = tags();
+        let mut transactions = Transactions::new();
+        for tr in [purchase(5, 3000, "food"), purchase(10, 90000, "rent"), purchase(20, 4500, "food")] {
+            transactions.add(tr);
+        }
+        assert_cache_matches(&transactions, &tags);
+
+        transactions.add(purchase(8, 1200, "food"));
+        assert_cache_matches(&transactions, &tags);
+
+        transactions.add(purchase(2, 700, "food"));
+        assert_cache_matches(&transactions, &tags);
+
+        transactions.add(purchase(20, 100, "rent"));
+        assert_cache_matches(&transactions, &tags);
+
+        let index = transactions.vec().iter().position(|tr| tr.date() == &day(10)).unwrap();
+        transactions.remove(index);
+        assert_cache_matches(&transactions, &tags);
+
+        transactions.replace(0, purchase(12, 800, "food"));
+        assert_cache_matches(&transactions, &tags);
+
+        transactions.remove(0);
+        assert_cache_matches(&transactions, &tags);
+    }
+}
@@ -1,13 +1,17 @@
 use std::error;
 use serde::{Serialize, de::DeserializeOwned};
-use std::fs::File;
-use std::path::Path;
-use std::fmt::{self, Display};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::fmt;
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     FileError(String, std::io::Error),
     YamlError(String, serde_yaml::Error),
+    WriteError(String, std::io::Error),
+    SerializeError(String, serde_yaml::Error),
 }
 
 impl fmt::Display for Error {
@@ -17,6 +21,10 @@ impl fmt::Display for Error {
                 write!(f, "Failed to open \"{filename}\": {err}"),
             Error::YamlError(ref filename, ref err) =>
                 write!(f, "Failed to parse \"{filename}\": {err}"),
+            Error::WriteError(ref filename, ref err) =>
+                write!(f, "Failed to write \"{filename}\": {err}"),
+            Error::SerializeError(ref filename, ref err) =>
+                write!(f, "Failed to serialize \"{filename}\": {err}"),
         }
     }
 }
@@ -26,17 +34,87 @@ impl error::Error for Error {
         match *self {
             Error::FileError(_, ref e) => Some(e),
             Error::YamlError(_, ref e) => Some(e),
+            Error::WriteError(_, ref e) => Some(e),
+            Error::SerializeError(_, ref e) => Some(e),
         }
     }
 }
 
+const BACKUP_DIR: &str = "backups";
+
+fn temp_path(path: &Path) -> PathBuf {
+    let name = path.file_name().map(|x| x.to_string_lossy().into_owned()).unwrap_or_default();
+    path.with_file_name(format!(".{name}.tmp"))
+}
+
+/// Copies `path` into the backup directory next to it, unless it was already
+/// saved today, and removes the backups of the same file older than the latest
+/// `days` ones. Each backup holds the file as it was before the first save of its day.
+fn backup(path: &Path, days: usize) -> Result<(), Error> {
+    let name = path.file_name().map(|x| x.to_string_lossy().into_owned()).unwrap_or_default();
+    let dir = path.with_file_name(BACKUP_DIR);
+    let write_error = |err| Error::WriteError(dir.display().to_string(), err);
+
+    fs::create_dir_all(&dir).map_err(write_error)?;
+
+    let day = chrono::Local::now().format("%Y%m%d");
+    let today = dir.join(format!("{name}.{day}"));
+    if today.exists() {
+        return Ok(());
+    }
+    fs::copy(path, today).map_err(write_error)?;
+
+    let prefix = format!("{name}.");
+    let mut backups: Vec<PathBuf> = fs::read_dir(&dir).map_err(write_error)?
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with(&prefix))
+        .map(|entry| entry.path())
+        .collect();
+    backups.sort();
+
+    let excess = backups.len().saturating_sub(days);
+    for old in &backups[..excess] {
+        fs::remove_file(old).map_err(write_error)?;
+    }
+
+    Ok(())
+}
+
 pub trait YamlRW: Serialize + DeserializeOwned {
-    fn read_yaml<P: Copy + Display + AsRef<Path>>(path: P) -> Result<Self, Error> {
-        Ok(serde_yaml::from_reader(File::open(path).map_err(|err| Error::FileError(path.to_string(), err))?).map_err(|err| Error::YamlError(path.to_string(), err))?)
+    fn read_yaml<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        serde_yaml::from_reader(File::open(path).map_err(|err| Error::FileError(path.display().to_string(), err))?).map_err(|err| Error::YamlError(path.display().to_string(), err))
     }
 
-    fn write_yaml<P: Copy + Display + AsRef<Path>>(&self, path: P) -> Result<(), Error> {
-        serde_yaml::to_writer(File::create(path).map_err(|err| Error::FileError(path.to_string(), err))?, &self).unwrap();
-        Ok(())
+    /// Writes to a temporary file first and renames it over `path`, so that
+    /// `path` always holds either the previous or the new content.
+    fn write_yaml<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let temp = temp_path(path);
+        let write_error = |err| Error::WriteError(path.display().to_string(), err);
+
+        let result = (|| {
+            let mut writer = BufWriter::new(File::create(&temp).map_err(write_error)?);
+            serde_yaml::to_writer(&mut writer, &self).map_err(|err| Error::SerializeError(path.display().to_string(), err))?;
+            writer.flush().map_err(write_error)?;
+            writer.get_ref().sync_all().map_err(write_error)?;
+            fs::rename(&temp, path).map_err(write_error)
+        })();
+
+        if result.is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+
+        result
+    }
+
+    /// Same as `write_yaml`, keeping one previous version per day for the
+    /// latest `days` days the file was saved on.
+    fn write_yaml_with_backup<P: AsRef<Path>>(&self, path: P, days: usize) -> Result<(), Error> {
+        let path = path.as_ref();
+        if path.exists() {
+            backup(path, days)?;
+        }
+        self.write_yaml(path)
     }
 }